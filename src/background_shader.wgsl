@@ -0,0 +1,103 @@
+struct BackgroundUniform {
+    // 0: 透明 1: 単色 2: 縦のグラデーション 3: 円形のグラデーション 4: 画像
+    kind: u32,
+    layerCount: u32,
+    time: f32,
+    _padding0: f32,
+    // 単色・グラデーションの上端（円形なら中心）の色
+    colorA: vec4<f32>,
+    // グラデーションの下端（円形なら外側）の色
+    colorB: vec4<f32>,
+    // 描画先の大きさ（ピクセル）
+    screenSize: vec2<f32>,
+    // カメラの位置（画面の高さを1とした単位）
+    camera: vec2<f32>,
+}
+
+@binding(0) @group(0) var<uniform> background: BackgroundUniform;
+@binding(1) @group(0) var image: texture_2d<f32>;
+@binding(2) @group(0) var imageSampler: sampler;
+
+const KIND_SOLID: u32 = 1u;
+const KIND_VERTICAL: u32 = 2u;
+const KIND_RADIAL: u32 = 3u;
+const KIND_IMAGE: u32 = 4u;
+const TAU: f32 = 6.28318531;
+
+// 画面全体を覆う三角形
+@vertex
+fn vertexMain(@builtin(vertex_index) vertexIdx: u32) -> @builtin(position) vec4<f32> {
+    let uv = vec2<f32>(f32((vertexIdx << 1u) & 2u), f32(vertexIdx & 2u));
+    return vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+}
+
+fn premultiply(color: vec4<f32>) -> vec4<f32> {
+    return vec4<f32>(color.rgb * color.a, color.a);
+}
+
+fn hash(cell: vec2<f32>, seed: f32) -> vec3<f32> {
+    var p = fract(vec3<f32>(cell.x, cell.y, seed) * vec3<f32>(0.1031, 0.1030, 0.0973));
+    p += dot(p, p.yxz + 33.33);
+    return fract((p.xxy + p.yxx) * p.zyx);
+}
+
+// 画像を縦横比を保ったまま画面を覆うように貼る
+fn imageColor(uv: vec2<f32>) -> vec4<f32> {
+    let imageSize = vec2<f32>(textureDimensions(image));
+    let scale = background.screenSize / imageSize;
+    let fit = scale / max(scale.x, scale.y);
+    return textureSample(image, imageSampler, (uv - 0.5) * fit + 0.5);
+}
+
+// 奥行きの層ごとに、カメラに合わせてずれる小さな星を並べる
+fn starLayers(pixel: vec2<f32>) -> f32 {
+    var alpha = 0.0;
+    for (var i = 0u; i < background.layerCount; i++) {
+        let depth = f32(i + 1u);
+        // 奥の層ほどカメラに対してゆっくり動き、星が小さく密になる
+        let parallax = 1.0 / depth;
+        let density = 10.0 * depth;
+        let grid = (pixel / background.screenSize.y + background.camera * parallax) * density;
+        let cell = floor(grid);
+        let h = hash(cell, depth);
+        if h.x > 0.5 {
+            continue;
+        }
+        let offset = 0.2 + 0.6 * h.yz;
+        let distance = length(fract(grid) - offset) / density * background.screenSize.y;
+        let radius = mix(0.6, 1.6, h.y) / sqrt(depth);
+        let twinkle = 0.75 + 0.25 * sin(background.time * (0.5 + 2.0 * h.z) + h.x * TAU);
+        let star = clamp(radius - distance + 0.5, 0.0, 1.0) * twinkle * mix(0.4, 1.0, h.z);
+        alpha = star + alpha * (1.0 - star);
+    }
+    return alpha;
+}
+
+@fragment
+fn fragmentMain(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let uv = position.xy / background.screenSize;
+
+    var color = vec4<f32>(0.0);
+    switch background.kind {
+        case KIND_SOLID: {
+            color = premultiply(background.colorA);
+        }
+        case KIND_VERTICAL: {
+            color = premultiply(mix(background.colorA, background.colorB, uv.y));
+        }
+        case KIND_RADIAL: {
+            let aspect = vec2<f32>(background.screenSize.x / background.screenSize.y, 1.0);
+            // 中心から画面の角までを0〜1とする
+            let t = length((uv - 0.5) * aspect) / length(0.5 * aspect);
+            color = premultiply(mix(background.colorA, background.colorB, clamp(t, 0.0, 1.0)));
+        }
+        case KIND_IMAGE: {
+            color = premultiply(imageColor(uv));
+        }
+        default: {}
+    }
+
+    // 乗算済みアルファで白い星を重ねる
+    let star = starLayers(position.xy);
+    return vec4<f32>(1.0, 1.0, 1.0, 1.0) * star + color * (1.0 - star);
+}
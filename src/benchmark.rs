@@ -0,0 +1,172 @@
+use std::time::{Duration, Instant};
+
+use crate::ctx::sdf_star::StarRenderMode;
+use crate::ctx::wgpu_star_ctx::WgpuStarCtx;
+use crate::simulation::SimulationMode;
+
+/// 計測する星の数
+const STAR_COUNTS: [u32; 4] = [1_000, 10_000, 100_000, 1_000_000];
+/// 計測前に捨てるフレーム数
+const WARMUP_FRAMES: u32 = 5;
+const WIDTH: u32 = 1280;
+const HEIGHT: u32 = 720;
+
+/// 1つの条件での計測結果
+struct BenchmarkRun {
+    render_mode: StarRenderMode,
+    simulation: SimulationMode,
+    stars: u32,
+    frame_times: Vec<Duration>,
+    gpu_times: Vec<Duration>,
+}
+
+/// ミリ秒でのパーセンタイル（最近傍順位法）
+fn percentile(sorted: &[Duration], p: f64) -> f64 {
+    let rank = ((p / 100.0) * sorted.len() as f64).ceil().max(1.0) as usize;
+    sorted[rank.min(sorted.len()) - 1].as_secs_f64() * 1000.0
+}
+
+fn summary_json(samples: &[Duration]) -> String {
+    if samples.is_empty() {
+        return "null".to_string();
+    }
+    let mut sorted = samples.to_vec();
+    sorted.sort();
+    let mean = sorted.iter().sum::<Duration>().as_secs_f64() * 1000.0 / sorted.len() as f64;
+    format!(
+        "{{\"min\": {:.4}, \"p50\": {:.4}, \"p90\": {:.4}, \"p95\": {:.4}, \"p99\": {:.4}, \"max\": {:.4}, \"mean\": {:.4}}}",
+        percentile(&sorted, 0.0),
+        percentile(&sorted, 50.0),
+        percentile(&sorted, 90.0),
+        percentile(&sorted, 95.0),
+        percentile(&sorted, 99.0),
+        percentile(&sorted, 100.0),
+        mean
+    )
+}
+
+fn json_string(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len() + 2);
+    escaped.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+fn run_one(
+    ctx: &mut WgpuStarCtx,
+    render_mode: StarRenderMode,
+    simulation: SimulationMode,
+    stars: u32,
+    frames: u32,
+) -> BenchmarkRun {
+    ctx.render_mode = render_mode;
+    ctx.simulation = simulation;
+    ctx.set_star_count(stars);
+    ctx.stats.take_latest_gpu_time();
+
+    let mut frame_times = Vec::with_capacity(frames as usize);
+    let mut gpu_times = Vec::with_capacity(frames as usize);
+    for frame in 0..WARMUP_FRAMES + frames {
+        let start = Instant::now();
+        ctx.draw();
+        // GPUの処理が終わるまで待って1フレームとする
+        ctx.device.poll(wgpu::Maintain::Wait);
+        let elapsed = start.elapsed();
+        if frame >= WARMUP_FRAMES {
+            frame_times.push(elapsed);
+            gpu_times.extend(ctx.stats.take_latest_gpu_time());
+        }
+    }
+
+    BenchmarkRun {
+        render_mode,
+        simulation,
+        stars,
+        frame_times,
+        gpu_times,
+    }
+}
+
+/// 星の数を増やしながらヘッドレスで描画し、フレーム時間をJSONで返す
+pub fn run(frames: u32) -> String {
+    let mut ctx = WgpuStarCtx::new_headless(WIDTH, HEIGHT);
+    let adapter = ctx.adapter.get_info();
+
+    let runs: Vec<String> = [StarRenderMode::Mesh, StarRenderMode::Sdf, StarRenderMode::Warp, StarRenderMode::Lit]
+        .into_iter()
+        .flat_map(|render_mode| [SimulationMode::Gpu, SimulationMode::Cpu].map(|simulation| (render_mode, simulation)))
+        .flat_map(|(render_mode, simulation)| STAR_COUNTS.map(|stars| (render_mode, simulation, stars)))
+        .map(|(render_mode, simulation, stars)| {
+            eprintln!(
+                "benchmark: {} render, {} simulation, {} stars",
+                render_mode.name(),
+                simulation.name(),
+                stars
+            );
+            let run = run_one(&mut ctx, render_mode, simulation, stars, frames);
+            format!(
+                "    {{\"render\": {}, \"simulation\": {}, \"stars\": {}, \"frames\": {}, \"frame_ms\": {}, \"gpu_ms\": {}}}",
+                json_string(run.render_mode.name()),
+                json_string(run.simulation.name()),
+                run.stars,
+                run.frame_times.len(),
+                summary_json(&run.frame_times),
+                summary_json(&run.gpu_times)
+            )
+        })
+        .collect();
+
+    format!(
+        "{{\n  \"adapter\": {},\n  \"backend\": {},\n  \"width\": {},\n  \"height\": {},\n  \"runs\": [\n{}\n  ]\n}}",
+        json_string(&adapter.name),
+        json_string(&format!("{:?}", adapter.backend)),
+        WIDTH,
+        HEIGHT,
+        runs.join(",\n")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn millis(values: &[u64]) -> Vec<Duration> {
+        values.iter().map(|ms| Duration::from_millis(*ms)).collect()
+    }
+
+    #[test]
+    fn percentile_uses_nearest_rank() {
+        let sorted = millis(&[1, 2, 3, 4, 5, 6, 7, 8, 9, 10]);
+        assert_eq!(percentile(&sorted, 0.0), 1.0);
+        assert_eq!(percentile(&sorted, 50.0), 5.0);
+        assert_eq!(percentile(&sorted, 90.0), 9.0);
+        assert_eq!(percentile(&sorted, 95.0), 10.0);
+        assert_eq!(percentile(&sorted, 100.0), 10.0);
+        assert_eq!(percentile(&millis(&[7]), 99.0), 7.0);
+    }
+
+    #[test]
+    fn summary_sorts_samples() {
+        assert_eq!(
+            summary_json(&millis(&[4, 1, 3, 2])),
+            "{\"min\": 1.0000, \"p50\": 2.0000, \"p90\": 4.0000, \"p95\": 4.0000, \"p99\": 4.0000, \"max\": 4.0000, \"mean\": 2.5000}"
+        );
+        assert_eq!(summary_json(&[]), "null");
+    }
+
+    #[test]
+    fn json_string_escapes_quotes_and_control_characters() {
+        assert_eq!(json_string("GeForce \"RTX\""), "\"GeForce \\\"RTX\\\"\"");
+        assert_eq!(json_string("a\\b"), "\"a\\\\b\"");
+        assert_eq!(json_string("line\nbreak\t"), "\"line\\u000abreak\\u0009\"");
+        assert_eq!(json_string("llvmpipe (LLVM 15.0.7, 256 bits)"), "\"llvmpipe (LLVM 15.0.7, 256 bits)\"");
+    }
+}
@@ -0,0 +1,74 @@
+struct BloomParams {
+    // ブラーの方向（読み込むテクスチャの1テクセル単位）
+    direction: vec2<f32>,
+    threshold: f32,
+    intensity: f32,
+    // ブラーの半径（読み込むテクスチャのテクセル数）
+    radius: f32,
+}
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@binding(0) @group(0) var source: texture_2d<f32>;
+@binding(1) @group(0) var linearSampler: sampler;
+@binding(2) @group(0) var<uniform> params: BloomParams;
+// 合成の時だけ使う、ぼかした光
+@binding(3) @group(0) var bloomTexture: texture_2d<f32>;
+
+// MAX_BLUR_TAPS（片側に読むテクセル数の上限）は ctx/bloom.rs が先頭に足す
+
+// 画面全体を覆う三角形
+@vertex
+fn vertexMain(@builtin(vertex_index) vertexIdx: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32((vertexIdx << 1u) & 2u), f32(vertexIdx & 2u));
+    var out: VertexOutput;
+    out.position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    out.uv = uv;
+    return out;
+}
+
+fn luminance(color: vec3<f32>) -> f32 {
+    return dot(color, vec3<f32>(0.2126, 0.7152, 0.0722));
+}
+
+// しきい値より明るい部分だけを取り出す（縮小も兼ねる）
+@fragment
+fn fragmentBrightPass(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(source, linearSampler, in.uv);
+    let brightness = luminance(color.rgb);
+    let contribution = max(brightness - params.threshold, 0.0) / max(brightness, 1e-4);
+    return color * contribution;
+}
+
+// 一方向のガウスぼかし（横と縦の2回で2次元のぼかしになる）
+@fragment
+fn fragmentBlur(in: VertexOutput) -> @location(0) vec4<f32> {
+    let texel = params.direction / vec2<f32>(textureDimensions(source));
+    let taps = min(i32(ceil(params.radius)), MAX_BLUR_TAPS);
+    let sigma = max(params.radius * 0.5, 0.5);
+
+    var sum = textureSample(source, linearSampler, in.uv);
+    var weightSum = 1.0;
+    for (var i = 1; i <= MAX_BLUR_TAPS; i++) {
+        if i > taps {
+            break;
+        }
+        let offset = f32(i);
+        let weight = exp(-offset * offset / (2.0 * sigma * sigma));
+        sum += textureSample(source, linearSampler, in.uv + texel * offset) * weight;
+        sum += textureSample(source, linearSampler, in.uv - texel * offset) * weight;
+        weightSum += weight * 2.0;
+    }
+    return sum / weightSum;
+}
+
+// 元の描画にぼかした光を足す
+@fragment
+fn fragmentComposite(in: VertexOutput) -> @location(0) vec4<f32> {
+    let scene = textureSample(source, linearSampler, in.uv);
+    let bloom = textureSample(bloomTexture, linearSampler, in.uv) * params.intensity;
+    return clamp(scene + bloom, vec4<f32>(0.0), vec4<f32>(1.0));
+}
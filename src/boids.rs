@@ -0,0 +1,208 @@
+use crate::{emitter::StarLife, spatial::SpatialGrid, vertex::instance::StarInstance};
+
+/// 群れで飛ぶ星の設定
+/// 距離は描画先の座標（-1.0..1.0）、重みは加速度（単位/秒²）で表す
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BoidsSettings {
+    /// 仲間として見える半径
+    pub radius: f32,
+    /// これより近い仲間からは離れる
+    pub separation_radius: f32,
+    /// 近すぎる仲間から離れる強さ
+    pub separation: f32,
+    /// 仲間と向きを揃える強さ
+    pub alignment: f32,
+    /// 仲間の中心へ寄る強さ
+    pub cohesion: f32,
+    pub min_speed: f32,
+    pub max_speed: f32,
+}
+
+impl Default for BoidsSettings {
+    fn default() -> Self {
+        Self {
+            radius: 0.15,
+            separation_radius: 0.05,
+            separation: 3.0,
+            alignment: 1.0,
+            cohesion: 1.0,
+            min_speed: 0.1,
+            max_speed: 0.5,
+        }
+    }
+}
+
+/// 1羽が気にする仲間の数の上限（密集しても計算が増えすぎないようにする）
+const MAX_NEIGHBORS: usize = 16;
+
+/// 見える範囲の仲間から、分離・整列・結合の3つの力を求めて dt 秒だけ加速する
+/// `lives` はエミッターや花火の枠の年齢と寿命で、寿命の尽きた星は仲間に数えない
+/// `grid` は呼び出しをまたいで使い回し、確保し直しを避ける
+pub fn apply_boids(
+    instances: &mut [StarInstance],
+    lives: Option<&[StarLife]>,
+    settings: &BoidsSettings,
+    grid: &mut SpatialGrid,
+    dt: f32,
+) {
+    grid.rebuild(instances.iter().map(|star| star.position), settings.radius);
+    let (radius_sq, separation_sq) = (settings.radius.powi(2), settings.separation_radius.powi(2));
+
+    // 全員が同じ時点の様子を見て動くように、加速度を求めてからまとめて速度を変える
+    let steering: Vec<[f32; 2]> = instances
+        .iter()
+        .enumerate()
+        .map(|(i, star)| {
+            // 見える範囲の仲間のうち近い順に MAX_NEIGHBORS 羽（距離の2乗、向き、番号）
+            // 探す順に先着で選ぶと、格子の下の行から選ばれて群れが下へ流れる
+            let mut nearest = [(0.0, [0.0; 2], 0); MAX_NEIGHBORS];
+            let mut count = 0;
+            grid.for_each_near(star.position, settings.radius, |j| {
+                let other = &instances[j];
+                let d = [other.position[0] - star.position[0], other.position[1] - star.position[1]];
+                let distance_sq = d[0] * d[0] + d[1] * d[1];
+                let alive = lives.is_none_or(|lives| lives[j].is_alive());
+                if j == i || distance_sq >= radius_sq || !alive {
+                    return;
+                }
+                if count == MAX_NEIGHBORS && distance_sq >= nearest[count - 1].0 {
+                    return;
+                }
+                // 満杯なら一番遠い仲間を追い出し、遠い方からずらして入れる
+                let mut k = count.min(MAX_NEIGHBORS - 1);
+                while k > 0 && nearest[k - 1].0 > distance_sq {
+                    nearest[k] = nearest[k - 1];
+                    k -= 1;
+                }
+                nearest[k] = (distance_sq, d, j);
+                count = (count + 1).min(MAX_NEIGHBORS);
+            });
+            let (mut center, mut velocity, mut away) = ([0.0; 2], [0.0; 2], [0.0; 2]);
+            for &(distance_sq, d, j) in &nearest[..count] {
+                for axis in 0..2 {
+                    center[axis] += d[axis];
+                    velocity[axis] += instances[j].speed[axis];
+                }
+                // 近いほど強く押し返す
+                if distance_sq < separation_sq && distance_sq > 1e-12 {
+                    away[0] -= d[0] / distance_sq;
+                    away[1] -= d[1] / distance_sq;
+                }
+            }
+            if count == 0 {
+                return [0.0, 0.0];
+            }
+            let n = count as f32;
+            let separation = normalize(away);
+            let alignment = normalize([velocity[0] / n - star.speed[0], velocity[1] / n - star.speed[1]]);
+            let cohesion = normalize([center[0] / n, center[1] / n]);
+            [0, 1].map(|axis| {
+                separation[axis] * settings.separation
+                    + alignment[axis] * settings.alignment
+                    + cohesion[axis] * settings.cohesion
+            })
+        })
+        .collect();
+
+    for (star, accel) in instances.iter_mut().zip(steering) {
+        star.speed[0] += accel[0] * dt;
+        star.speed[1] += accel[1] * dt;
+        let speed = (star.speed[0] * star.speed[0] + star.speed[1] * star.speed[1]).sqrt();
+        if speed < 1e-6 {
+            star.speed = [settings.min_speed, 0.0];
+        } else {
+            let clamped = speed.clamp(settings.min_speed, settings.max_speed);
+            star.speed = star.speed.map(|s| s * clamped / speed);
+        }
+    }
+}
+
+/// 星の頂点の1つを進む向きに向ける
+/// 頂点は +x の向きにあり、シェーダーは `initial_rotation` だけ時計回りに回すので、角度の符号を逆にする
+pub fn face_heading(instances: &mut [StarInstance]) {
+    for star in instances.iter_mut() {
+        star.initial_rotation = (-star.speed[1]).atan2(star.speed[0]).rem_euclid(std::f32::consts::TAU);
+    }
+}
+
+fn normalize(v: [f32; 2]) -> [f32; 2] {
+    let length = (v[0] * v[0] + v[1] * v[1]).sqrt();
+    if length < 1e-6 {
+        [0.0, 0.0]
+    } else {
+        [v[0] / length, v[1] / length]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn boid(position: [f32; 2], speed: [f32; 2]) -> StarInstance {
+        StarInstance { position, speed, scale: 0.05, ..bytemuck::Zeroable::zeroed() }
+    }
+
+    fn only(separation: f32, alignment: f32, cohesion: f32) -> BoidsSettings {
+        BoidsSettings { separation, alignment, cohesion, min_speed: 0.0, max_speed: 10.0, ..Default::default() }
+    }
+
+    fn step(instances: &mut [StarInstance], settings: &BoidsSettings) {
+        apply_boids(instances, None, settings, &mut SpatialGrid::default(), 0.1);
+    }
+
+    #[test]
+    fn separation_pushes_close_stars_apart() {
+        let mut instances = vec![boid([0.0, 0.0], [0.0, 0.2]), boid([0.02, 0.0], [0.0, 0.2])];
+        step(&mut instances, &only(1.0, 0.0, 0.0));
+        assert!(instances[0].speed[0] < 0.0 && instances[1].speed[0] > 0.0, "{:?}", instances[0].speed);
+    }
+
+    #[test]
+    fn alignment_turns_towards_neighbor_velocity() {
+        let mut instances = vec![
+            boid([0.0, 0.0], [0.2, 0.0]),
+            boid([0.1, 0.0], [0.0, 0.2]),
+            boid([-0.1, 0.0], [0.0, 0.2]),
+        ];
+        step(&mut instances, &only(0.0, 1.0, 0.0));
+        assert!(instances[0].speed[0] < 0.2 && instances[0].speed[1] > 0.0, "{:?}", instances[0].speed);
+    }
+
+    #[test]
+    fn expired_slots_are_not_neighbors() {
+        let mut instances = vec![boid([0.0, 0.0], [0.2, 0.0]), boid([0.02, 0.0], [0.0, 0.2])];
+        let lives = [StarLife::FOREVER, StarLife::EMPTY];
+        apply_boids(&mut instances, Some(&lives), &only(1.0, 1.0, 1.0), &mut SpatialGrid::default(), 0.1);
+        assert!((instances[0].speed[0] - 0.2).abs() < 1e-6 && instances[0].speed[1] == 0.0, "{:?}", instances[0].speed);
+    }
+
+    #[test]
+    fn speed_is_clamped_between_min_and_max() {
+        let settings = BoidsSettings::default();
+        let mut instances = vec![boid([-0.5, 0.0], [2.0, 0.0]), boid([0.5, 0.0], [0.0, -0.01])];
+        step(&mut instances, &settings);
+        assert!((instances[0].speed[0] - settings.max_speed).abs() < 1e-6, "{:?}", instances[0].speed);
+        assert!((instances[1].speed[1] + settings.min_speed).abs() < 1e-6, "{:?}", instances[1].speed);
+    }
+
+    #[test]
+    fn stopped_star_starts_moving_at_min_speed() {
+        let settings = BoidsSettings::default();
+        let mut instances = vec![boid([0.0, 0.0], [0.0, 0.0])];
+        step(&mut instances, &settings);
+        assert_eq!(instances[0].speed, [settings.min_speed, 0.0]);
+    }
+
+    #[test]
+    fn neighbor_cap_does_not_pull_towards_one_side() {
+        // 上下に対になった仲間が上限より多くいても、選ばれるのは近い対なので上下に偏らない
+        let mut instances = vec![boid([0.0, 0.0], [0.2, 0.0])];
+        for k in 0..MAX_NEIGHBORS {
+            let (x, y) = (0.005 * k as f32, 0.02 + 0.004 * k as f32);
+            instances.push(boid([x, -y], [0.2, 0.0]));
+            instances.push(boid([x, y], [0.2, 0.0]));
+        }
+        step(&mut instances, &only(0.0, 0.0, 1.0));
+        assert!(instances[0].speed[1].abs() < 1e-4, "{:?}", instances[0].speed);
+    }
+}
@@ -0,0 +1,75 @@
+use std::time::Instant;
+
+/// 一時停止やコマ送り、速さの変更ができるアニメーションの時計
+#[derive(Clone, Debug)]
+pub struct Clock {
+    /// 最後に時刻を読んだ時の実時間
+    last_tick: Instant,
+    /// アニメーションの経過秒数
+    time: f32,
+    /// 実時間に対する進む速さの倍率
+    pub speed: f32,
+    pub paused: bool,
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock {
+    const MIN_SPEED: f32 = 1.0 / 8.0;
+    const MAX_SPEED: f32 = 8.0;
+    /// コマ送りで進める秒数
+    pub const STEP: f32 = 1.0 / 60.0;
+
+    pub fn new() -> Self {
+        Self {
+            last_tick: Instant::now(),
+            time: 0.0,
+            speed: 1.0,
+            paused: false,
+        }
+    }
+
+    /// 前に読んだ時からの実時間の経過を反映して、今の時刻を返す
+    pub fn tick(&mut self) -> f32 {
+        let now = Instant::now();
+        if !self.paused {
+            self.time += (now - self.last_tick).as_secs_f32() * self.speed;
+        }
+        self.last_tick = now;
+        self.time
+    }
+
+    /// 時刻を0に戻す
+    pub fn reset(&mut self) {
+        self.last_tick = Instant::now();
+        self.time = 0.0;
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.tick();
+        self.paused = !self.paused;
+    }
+
+    /// 一時停止して1コマだけ進める
+    pub fn step(&mut self) {
+        self.tick();
+        self.paused = true;
+        self.time += Self::STEP;
+    }
+
+    /// 速さを2倍にする
+    pub fn speed_up(&mut self) {
+        self.tick();
+        self.speed = (self.speed * 2.0).min(Self::MAX_SPEED);
+    }
+
+    /// 速さを半分にする
+    pub fn slow_down(&mut self) {
+        self.tick();
+        self.speed = (self.speed * 0.5).max(Self::MIN_SPEED);
+    }
+}
@@ -0,0 +1,118 @@
+use crate::{spatial::SpatialGrid, vertex::instance::StarInstance};
+
+/// 2つの星が重なっていれば押し離し、近づいているなら弾性衝突で速度を入れ替える
+/// 星は半径 `scale` の円とみなし、質量は面積に比例させる
+fn collide(instances: &mut [StarInstance], i: usize, j: usize) {
+    let (head, tail) = instances.split_at_mut(j);
+    let (a, b) = (&mut head[i], &mut tail[0]);
+    let d = [b.position[0] - a.position[0], b.position[1] - a.position[1]];
+    let distance_sq = d[0] * d[0] + d[1] * d[1];
+    let min_distance = a.scale + b.scale;
+    // ぴったり重なった星は押す向きが決まらないので触らない
+    if distance_sq >= min_distance * min_distance || distance_sq < 1e-12 {
+        return;
+    }
+    let distance = distance_sq.sqrt();
+    let normal = [d[0] / distance, d[1] / distance];
+    let (mass_a, mass_b) = (a.scale.powi(2).max(1e-6), b.scale.powi(2).max(1e-6));
+    let total = mass_a + mass_b;
+
+    let overlap = min_distance - distance;
+    for (axis, n) in normal.iter().enumerate() {
+        a.position[axis] -= n * overlap * mass_b / total;
+        b.position[axis] += n * overlap * mass_a / total;
+    }
+
+    let approach = (b.speed[0] - a.speed[0]) * normal[0] + (b.speed[1] - a.speed[1]) * normal[1];
+    if approach >= 0.0 {
+        return;
+    }
+    let impulse = 2.0 * approach / (1.0 / mass_a + 1.0 / mass_b);
+    for (axis, n) in normal.iter().enumerate() {
+        a.speed[axis] += n * impulse / mass_a;
+        b.speed[axis] -= n * impulse / mass_b;
+    }
+}
+
+/// 重なった星どうしを衝突させる
+/// `EdgeMode::Wrap` でも端をまたいだ星どうしは衝突させない（画面の反対側に描かれるので、離れて見える）
+/// `grid` は呼び出しをまたいで使い回し、確保し直しを避ける
+pub fn resolve_collisions(instances: &mut [StarInstance], grid: &mut SpatialGrid) {
+    let max_radius = instances.iter().map(|star| star.scale).fold(0.0, f32::max);
+    if max_radius <= 0.0 {
+        return;
+    }
+    grid.rebuild(instances.iter().map(|star| star.position), max_radius * 2.0);
+    for i in 0..instances.len() {
+        let (position, radius) = (instances[i].position, instances[i].scale);
+        grid.for_each_near(position, radius + max_radius, |j| {
+            if j > i {
+                collide(instances, i, j);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn star(position: [f32; 2], speed: [f32; 2], scale: f32) -> StarInstance {
+        StarInstance { position, speed, scale, ..bytemuck::Zeroable::zeroed() }
+    }
+
+    fn motion(instances: &[StarInstance]) -> Vec<([f32; 2], [f32; 2])> {
+        instances.iter().map(|star| (star.position, star.speed)).collect()
+    }
+
+    fn momentum(instances: &[StarInstance]) -> [f32; 2] {
+        [0, 1].map(|axis| instances.iter().map(|star| star.scale.powi(2) * star.speed[axis]).sum())
+    }
+
+    #[test]
+    fn elastic_collision_conserves_momentum_and_energy() {
+        let mut instances = vec![star([-0.05, 0.0], [1.0, 0.2], 0.1), star([0.05, 0.03], [-0.5, 0.0], 0.05)];
+        let energy = |instances: &[StarInstance]| -> f32 {
+            instances.iter().map(|star| star.scale.powi(2) * (star.speed[0].powi(2) + star.speed[1].powi(2))).sum()
+        };
+        let (before, energy_before) = (momentum(&instances), energy(&instances));
+        resolve_collisions(&mut instances, &mut SpatialGrid::default());
+        let after = momentum(&instances);
+        assert!((before[0] - after[0]).abs() < 1e-6 && (before[1] - after[1]).abs() < 1e-6, "{:?} {:?}", before, after);
+        assert!((energy_before - energy(&instances)).abs() < 1e-6);
+        // 正面から近づいていた星は離れていく
+        assert!(instances[0].speed[0] < instances[1].speed[0]);
+    }
+
+    #[test]
+    fn overlapping_stars_are_pushed_apart() {
+        let mut instances = vec![star([0.0, 0.0], [0.0, 0.0], 0.1), star([0.1, 0.0], [0.0, 0.0], 0.1)];
+        resolve_collisions(&mut instances, &mut SpatialGrid::default());
+        let distance = instances[1].position[0] - instances[0].position[0];
+        assert!((distance - 0.2).abs() < 1e-6, "{}", distance);
+        // 離れていくだけなので速度は変わらない
+        assert_eq!(instances[0].speed, [0.0, 0.0]);
+    }
+
+    #[test]
+    fn separated_and_receding_stars_are_untouched() {
+        let mut instances = vec![
+            star([-0.5, 0.0], [1.0, 0.0], 0.1),
+            star([0.5, 0.0], [-1.0, 0.0], 0.1),
+            // 触れているが離れていく星
+            star([0.0, 0.5], [0.0, -1.0], 0.1),
+            star([0.0, 0.7], [0.0, 1.0], 0.1),
+        ];
+        let before = motion(&instances);
+        resolve_collisions(&mut instances, &mut SpatialGrid::default());
+        assert_eq!(motion(&instances), before);
+    }
+
+    #[test]
+    fn stars_across_the_wrap_seam_do_not_collide() {
+        let mut instances = vec![star([-0.98, 0.0], [-1.0, 0.0], 0.05), star([0.98, 0.0], [1.0, 0.0], 0.05)];
+        let before = motion(&instances);
+        resolve_collisions(&mut instances, &mut SpatialGrid::default());
+        assert_eq!(motion(&instances), before);
+    }
+}
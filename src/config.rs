@@ -0,0 +1,398 @@
+use std::path::{Path, PathBuf};
+
+use crate::boids::BoidsSettings;
+use crate::ctx::background::{Background, BackgroundSettings};
+use crate::ctx::bloom::BloomSettings;
+use crate::ctx::depth::StarOrdering;
+use crate::ctx::lit_star::LightSettings;
+use crate::ctx::sdf_star::{SdfStyle, StarRenderMode};
+use crate::ctx::trails::TrailSettings;
+use crate::ctx::warp::CameraSettings;
+use crate::curves::LifetimeCurves;
+use crate::emitter::{Emitter, EmitterShape, ValueRange};
+use crate::fireworks::FireworksSettings;
+use crate::forces::ForceField;
+use crate::frame_pacing::FramePacing;
+use crate::interaction::{InteractionSettings, PointerForce};
+use crate::keybindings::KeyBindings;
+use crate::simulation::{EdgeMode, SimulationMode};
+
+/// 起動時の設定
+#[derive(Clone, Debug)]
+pub struct AppConfig {
+    pub pacing: FramePacing,
+    pub simulation: SimulationMode,
+    /// 描画先の端での振る舞い
+    pub edges: EdgeMode,
+    /// 星どうしを衝突させる
+    pub collisions: bool,
+    /// 星を加速する力の場（いくつでも重ねられる）
+    pub forces: Vec<ForceField>,
+    /// 群れで飛ぶ（`None` なら使わない）
+    pub boids: Option<BoidsSettings>,
+    /// 星を生み出すエミッター（空なら決まった数の星を置く）
+    pub emitters: Vec<Emitter>,
+    /// エミッターと花火の星を入れる枠の数
+    pub emitter_capacity: u32,
+    /// 花火（`None` なら使わない）
+    pub fireworks: Option<FireworksSettings>,
+    /// 寿命に合わせて星の見た目と回転を変える曲線（シーンファイルで決める）
+    pub curves: Option<LifetimeCurves>,
+    /// MSAAのサンプル数（1/2/4/8）
+    pub msaa_samples: u32,
+    pub render_mode: StarRenderMode,
+    /// SDFで描く時の光彩と輪郭線
+    pub sdf_style: SdfStyle,
+    /// 3Dで描く時のカメラ
+    pub camera: CameraSettings,
+    /// 立体の星を照らす光源
+    pub light: LightSettings,
+    /// スプライトで描く時のアトラスのPNG
+    pub sprite_atlas: Option<PathBuf>,
+    /// アトラスの列数と行数
+    pub sprite_grid: (u32, u32),
+    /// 星空の後ろに描く背景
+    pub background: BackgroundSettings,
+    /// ブルーム（`None` なら使わない）
+    pub bloom: Option<BloomSettings>,
+    /// 星の軌跡（`None` なら使わない）
+    pub trails: Option<TrailSettings>,
+    /// 重なった星を描く順
+    pub depth_order: StarOrdering,
+    /// 枠の無い透明なウィンドウを最前面に開き、デスクトップの上に星を重ねる
+    pub transparent_window: bool,
+    /// 透明なウィンドウでマウス操作を後ろのウィンドウに通す（透明なウィンドウも有効になる）
+    pub click_through: bool,
+    /// マウスやタッチで星を動かしたり、追加・削除したり、拡大縮小したりできるようにする
+    pub interactive: bool,
+    pub interaction: InteractionSettings,
+    /// キーと操作の対応
+    pub keybindings: KeyBindings,
+    /// ウィンドウを開かずにベンチマークを実行する
+    pub benchmark: bool,
+    /// ベンチマークで1条件あたりに計測するフレーム数
+    pub benchmark_frames: u32,
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        Self {
+            pacing: FramePacing::default(),
+            simulation: SimulationMode::default(),
+            edges: EdgeMode::default(),
+            collisions: false,
+            forces: Vec::new(),
+            boids: None,
+            emitters: Vec::new(),
+            emitter_capacity: 4096,
+            fireworks: None,
+            curves: None,
+            msaa_samples: 1,
+            render_mode: StarRenderMode::default(),
+            sdf_style: SdfStyle::default(),
+            camera: CameraSettings::default(),
+            light: LightSettings::default(),
+            sprite_atlas: None,
+            sprite_grid: (1, 1),
+            background: BackgroundSettings::default(),
+            bloom: None,
+            trails: None,
+            depth_order: StarOrdering::default(),
+            transparent_window: false,
+            click_through: false,
+            interactive: false,
+            interaction: InteractionSettings::default(),
+            keybindings: KeyBindings::default(),
+            benchmark: false,
+            benchmark_frames: 120,
+        }
+    }
+}
+
+impl AppConfig {
+    /// コマンドライン引数から設定を読み取る
+    ///
+    /// `--pacing=vsync|fps:<N>|idle`
+    /// `--simulation=gpu|cpu`
+    /// `--edges=wrap|bounce|vanish` `--collisions` `--bouncing`（跳ね返って衝突するスクリーンセーバー）
+    /// `--force=gravity:<x>,<y>|wind:<x>,<y>[,<乱流>]|attractor:<x>,<y>,<強さ>[,<半径>]|repulsor:...|vortex:...|drag:<割合>`（繰り返し指定できる）
+    /// `--boids` `--boid-radius=<半径>` `--separation=<強さ>` `--alignment=<強さ>` `--cohesion=<強さ>`
+    /// `--emitter=point:<x>,<y>|line:<x1>,<y1>,<x2>,<y2>|circle:<x>,<y>,<半径>|rect:<x1>,<y1>,<x2>,<y2>`（繰り返し指定できる）
+    /// `--emit-rate=<毎秒>` `--emit-burst=<N>[,<間隔の秒>]` `--lifetime=<秒の範囲>` `--emit-speed=<範囲>`
+    /// `--emit-direction=<度の範囲>` `--emit-scale=<範囲>` `--emit-rotation=<度の範囲>` `--emit-spin=<ラジアン/秒の範囲>`
+    /// （`--emit-*` と `--lifetime` は直前の `--emitter` に効く。範囲は `<最小>..<最大>` か `<値>`）
+    /// `--emitter-capacity=<N>`
+    /// `--fireworks` `--launch-rate=<毎秒>` `--sparks=<N>` `--secondary-sparks=<N>`（0なら二度目は弾けない）
+    /// `--scene=<シーンファイル>`（寿命に合わせた大きさ・不透明度・色・回転の速さの曲線）
+    /// `--msaa=1|2|4|8`
+    /// `--render=mesh|sdf|sprite|warp|lit` `--glow=<強さ>` `--glow-radius=<広がり>` `--outline=<太さ>`
+    /// `--fov=<度>` `--warp-depth=<奥行き>` `--light-direction=<x>,<y>,<z>`
+    /// `--sprite-atlas=<PNG>` `--sprite-grid=<列数>x<行数>`
+    /// `--background=transparent|solid:<色>|vertical:<色>,<色>|radial:<色>,<色>|image:<PNG>`
+    /// `--parallax-layers=<N>`（8まで） `--camera-velocity=<x>,<y>`
+    /// `--bloom` `--bloom-intensity=<強さ>` `--bloom-threshold=<しきい値>` `--bloom-radius=<ピクセル>`（64まで）
+    /// `--trails` `--trail-length=<秒>` `--trail-decay=<1秒後に残る割合>`
+    /// `--depth-order=sorted|buffer`（ワープと立体の星は常に深度バッファを使う）
+    /// `--transparent` `--click-through`
+    /// `--interactive` `--pointer-force=attract|repel|none` `--pointer-radius=<半径>` `--pointer-strength=<加速度>`
+    /// `--burst=<N>` `--remove-radius=<半径>`
+    /// `--keys=<設定ファイル>`
+    /// `--benchmark` `--benchmark-frames=<N>`
+    pub fn from_args() -> Self {
+        let mut config = Self::default();
+        for arg in std::env::args().skip(1) {
+            let (key, value) = arg.split_once('=').unwrap_or((arg.as_str(), ""));
+            match key {
+                "--pacing" => match FramePacing::parse(value) {
+                    Some(pacing) => config.pacing = pacing,
+                    None => eprintln!("Unknown pacing mode: {:?}", value),
+                },
+                "--simulation" => match SimulationMode::parse(value) {
+                    Some(simulation) => config.simulation = simulation,
+                    None => eprintln!("Unknown simulation mode: {:?}", value),
+                },
+                "--edges" => match EdgeMode::parse(value) {
+                    Some(edges) => config.edges = edges,
+                    None => eprintln!("Unknown edge mode: {:?}", value),
+                },
+                "--collisions" => config.collisions = true,
+                "--bouncing" => {
+                    config.edges = EdgeMode::Bounce;
+                    config.collisions = true;
+                }
+                "--force" => match ForceField::parse(value) {
+                    Some(force) => config.forces.push(force),
+                    None => eprintln!("Invalid force field: {:?}", value),
+                },
+                "--boids" => {
+                    config.boids.get_or_insert_with(BoidsSettings::default);
+                }
+                "--boid-radius" => match value.parse::<f32>() {
+                    Ok(radius) if radius > 0.0 => config.boids.get_or_insert_with(BoidsSettings::default).radius = radius,
+                    _ => eprintln!("Invalid boid radius: {:?}", value),
+                },
+                "--separation" => match value.parse::<f32>() {
+                    Ok(weight) if weight >= 0.0 => {
+                        config.boids.get_or_insert_with(BoidsSettings::default).separation = weight
+                    }
+                    _ => eprintln!("Invalid separation weight: {:?}", value),
+                },
+                "--alignment" => match value.parse::<f32>() {
+                    Ok(weight) if weight >= 0.0 => {
+                        config.boids.get_or_insert_with(BoidsSettings::default).alignment = weight
+                    }
+                    _ => eprintln!("Invalid alignment weight: {:?}", value),
+                },
+                "--cohesion" => match value.parse::<f32>() {
+                    Ok(weight) if weight >= 0.0 => {
+                        config.boids.get_or_insert_with(BoidsSettings::default).cohesion = weight
+                    }
+                    _ => eprintln!("Invalid cohesion weight: {:?}", value),
+                },
+                "--emitter" => match EmitterShape::parse(value) {
+                    Some(shape) => config.emitters.push(Emitter::new(shape)),
+                    None => eprintln!("Invalid emitter: {:?}", value),
+                },
+                "--emit-rate" => match (value.parse::<f32>(), config.emitters.last_mut()) {
+                    (Ok(rate), Some(emitter)) if rate >= 0.0 => emitter.rate = rate,
+                    _ => eprintln!("Invalid emitter rate: {:?}", value),
+                },
+                "--emit-burst" => {
+                    let (count, interval) = value.split_once(',').unwrap_or((value, "0"));
+                    match (count.parse::<u32>(), interval.parse::<f32>(), config.emitters.last_mut()) {
+                        (Ok(count), Ok(interval), Some(emitter)) if interval >= 0.0 => {
+                            emitter.burst_count = count;
+                            emitter.burst_interval = interval;
+                        }
+                        _ => eprintln!("Invalid emitter burst: {:?}", value),
+                    }
+                }
+                "--lifetime" => match (ValueRange::parse(value), config.emitters.last_mut()) {
+                    (Some(lifetime), Some(emitter)) if lifetime.min > 0.0 => emitter.lifetime = lifetime,
+                    _ => eprintln!("Invalid lifetime: {:?}", value),
+                },
+                "--emit-speed" => match (ValueRange::parse(value), config.emitters.last_mut()) {
+                    (Some(speed), Some(emitter)) if speed.min >= 0.0 => emitter.speed = speed,
+                    _ => eprintln!("Invalid emitter speed: {:?}", value),
+                },
+                "--emit-direction" => match (ValueRange::parse(value), config.emitters.last_mut()) {
+                    (Some(degrees), Some(emitter)) => {
+                        emitter.direction = ValueRange::new(degrees.min.to_radians(), degrees.max.to_radians())
+                    }
+                    _ => eprintln!("Invalid emitter direction: {:?}", value),
+                },
+                "--emit-scale" => match (ValueRange::parse(value), config.emitters.last_mut()) {
+                    (Some(scale), Some(emitter)) if scale.min > 0.0 => emitter.scale = scale,
+                    _ => eprintln!("Invalid emitter scale: {:?}", value),
+                },
+                "--emit-rotation" => match (ValueRange::parse(value), config.emitters.last_mut()) {
+                    (Some(degrees), Some(emitter)) => {
+                        emitter.rotation = ValueRange::new(degrees.min.to_radians(), degrees.max.to_radians())
+                    }
+                    _ => eprintln!("Invalid emitter rotation: {:?}", value),
+                },
+                "--emit-spin" => match (ValueRange::parse(value), config.emitters.last_mut()) {
+                    (Some(spin), Some(emitter)) => emitter.rotation_speed = spin,
+                    _ => eprintln!("Invalid emitter spin: {:?}", value),
+                },
+                "--emitter-capacity" => match value.parse::<u32>() {
+                    Ok(capacity) if capacity > 0 => config.emitter_capacity = capacity,
+                    _ => eprintln!("Invalid emitter capacity: {:?}", value),
+                },
+                "--fireworks" => {
+                    config.fireworks.get_or_insert_with(FireworksSettings::default);
+                }
+                "--launch-rate" => match value.parse::<f32>() {
+                    Ok(rate) if rate >= 0.0 => config.fireworks.get_or_insert_with(FireworksSettings::default).launch_rate = rate,
+                    _ => eprintln!("Invalid launch rate: {:?}", value),
+                },
+                "--sparks" => match value.parse::<u32>() {
+                    Ok(count) => config.fireworks.get_or_insert_with(FireworksSettings::default).spark_count = count,
+                    _ => eprintln!("Invalid spark count: {:?}", value),
+                },
+                "--secondary-sparks" => match value.parse::<u32>() {
+                    Ok(count) => config.fireworks.get_or_insert_with(FireworksSettings::default).secondary_count = count,
+                    _ => eprintln!("Invalid secondary spark count: {:?}", value),
+                },
+                "--scene" => match LifetimeCurves::load(Path::new(value)) {
+                    Ok(curves) => config.curves = Some(curves),
+                    Err(err) => eprintln!("Failed to load scene {:?}: {}", value, err),
+                },
+                "--msaa" => match value.parse::<u32>() {
+                    Ok(samples @ (1 | 2 | 4 | 8)) => config.msaa_samples = samples,
+                    _ => eprintln!("Invalid MSAA sample count: {:?}", value),
+                },
+                "--render" => match StarRenderMode::parse(value) {
+                    Some(render_mode) => config.render_mode = render_mode,
+                    None => eprintln!("Unknown render mode: {:?}", value),
+                },
+                "--glow" => match value.parse::<f32>() {
+                    Ok(strength) if strength >= 0.0 => config.sdf_style.glow_strength = strength,
+                    _ => eprintln!("Invalid glow strength: {:?}", value),
+                },
+                "--glow-radius" => match value.parse::<f32>() {
+                    Ok(radius) if radius > 0.0 => config.sdf_style.glow_radius = radius,
+                    _ => eprintln!("Invalid glow radius: {:?}", value),
+                },
+                "--outline" => match value.parse::<f32>() {
+                    Ok(width) if width >= 0.0 => config.sdf_style.outline_width = width,
+                    _ => eprintln!("Invalid outline width: {:?}", value),
+                },
+                "--fov" => match value.parse::<f32>() {
+                    Ok(fov_y) if fov_y > 0.0 && fov_y < 180.0 => config.camera.fov_y = fov_y,
+                    _ => eprintln!("Invalid field of view: {:?}", value),
+                },
+                "--warp-depth" => match value.parse::<f32>() {
+                    Ok(far) if far > config.camera.near => config.camera.far = far,
+                    _ => eprintln!("Invalid warp depth: {:?}", value),
+                },
+                "--light-direction" => match value.split(',').map(str::parse::<f32>).collect::<Result<Vec<_>, _>>() {
+                    Ok(direction) if direction.len() == 3 && direction.iter().any(|d| *d != 0.0) => {
+                        config.light.direction = [direction[0], direction[1], direction[2]]
+                    }
+                    _ => eprintln!("Invalid light direction: {:?}", value),
+                },
+                "--sprite-atlas" if !value.is_empty() => config.sprite_atlas = Some(PathBuf::from(value)),
+                "--sprite-grid" => match value.split_once('x').map(|(c, r)| (c.parse::<u32>(), r.parse::<u32>())) {
+                    Some((Ok(columns), Ok(rows))) if columns > 0 && rows > 0 => config.sprite_grid = (columns, rows),
+                    _ => eprintln!("Invalid sprite grid: {:?}", value),
+                },
+                "--background" => match Background::parse(value) {
+                    Some(background) => config.background.background = background,
+                    None => eprintln!("Invalid background: {:?}", value),
+                },
+                "--parallax-layers" => match value.parse::<u32>() {
+                    Ok(layers) if layers <= BackgroundSettings::MAX_PARALLAX_LAYERS => {
+                        config.background.parallax_layers = layers
+                    }
+                    _ => eprintln!(
+                        "Invalid parallax layer count (at most {}): {:?}",
+                        BackgroundSettings::MAX_PARALLAX_LAYERS,
+                        value
+                    ),
+                },
+                "--camera-velocity" => match value.split_once(',').map(|(x, y)| (x.parse::<f32>(), y.parse::<f32>())) {
+                    Some((Ok(x), Ok(y))) => config.background.camera_velocity = [x, y],
+                    _ => eprintln!("Invalid camera velocity: {:?}", value),
+                },
+                "--bloom" => {
+                    config.bloom.get_or_insert_with(BloomSettings::default);
+                }
+                "--bloom-intensity" => match value.parse::<f32>() {
+                    Ok(intensity) if intensity >= 0.0 => {
+                        config.bloom.get_or_insert_with(BloomSettings::default).intensity = intensity
+                    }
+                    _ => eprintln!("Invalid bloom intensity: {:?}", value),
+                },
+                "--bloom-threshold" => match value.parse::<f32>() {
+                    Ok(threshold) if threshold >= 0.0 => {
+                        config.bloom.get_or_insert_with(BloomSettings::default).threshold = threshold
+                    }
+                    _ => eprintln!("Invalid bloom threshold: {:?}", value),
+                },
+                "--bloom-radius" => match value.parse::<f32>() {
+                    Ok(radius) if radius > 0.0 && radius <= BloomSettings::MAX_RADIUS => {
+                        config.bloom.get_or_insert_with(BloomSettings::default).radius = radius
+                    }
+                    _ => eprintln!("Invalid bloom radius (at most {}): {:?}", BloomSettings::MAX_RADIUS, value),
+                },
+                "--trails" => {
+                    config.trails.get_or_insert_with(TrailSettings::default);
+                }
+                "--trail-length" => match value.parse::<f32>() {
+                    Ok(length) if length > 0.0 => {
+                        config.trails.get_or_insert_with(TrailSettings::default).length = length
+                    }
+                    _ => eprintln!("Invalid trail length: {:?}", value),
+                },
+                "--trail-decay" => match value.parse::<f32>() {
+                    Ok(decay) if (0.0..=1.0).contains(&decay) => {
+                        config.trails.get_or_insert_with(TrailSettings::default).decay = decay
+                    }
+                    _ => eprintln!("Invalid trail decay: {:?}", value),
+                },
+                "--depth-order" => match StarOrdering::parse(value) {
+                    Some(depth_order) => config.depth_order = depth_order,
+                    None => eprintln!("Unknown depth order: {:?}", value),
+                },
+                "--transparent" => config.transparent_window = true,
+                "--click-through" => {
+                    config.transparent_window = true;
+                    config.click_through = true;
+                }
+                "--interactive" => config.interactive = true,
+                "--pointer-force" => match PointerForce::parse(value) {
+                    Some(force) => config.interaction.force = force,
+                    None => eprintln!("Unknown pointer force: {:?}", value),
+                },
+                "--pointer-radius" => match value.parse::<f32>() {
+                    Ok(radius) if radius > 0.0 => config.interaction.radius = radius,
+                    _ => eprintln!("Invalid pointer radius: {:?}", value),
+                },
+                "--pointer-strength" => match value.parse::<f32>() {
+                    Ok(strength) if strength >= 0.0 => config.interaction.strength = strength,
+                    _ => eprintln!("Invalid pointer strength: {:?}", value),
+                },
+                "--burst" => match value.parse::<u32>() {
+                    Ok(count) => config.interaction.burst_count = count,
+                    _ => eprintln!("Invalid burst count: {:?}", value),
+                },
+                "--remove-radius" => match value.parse::<f32>() {
+                    Ok(radius) if radius > 0.0 => config.interaction.remove_radius = radius,
+                    _ => eprintln!("Invalid remove radius: {:?}", value),
+                },
+                "--keys" => match KeyBindings::load(Path::new(value)) {
+                    Ok(keybindings) => config.keybindings = keybindings,
+                    Err(err) => eprintln!("Failed to load key bindings {:?}: {}", value, err),
+                },
+                "--benchmark" => config.benchmark = true,
+                "--benchmark-frames" => match value.parse::<u32>() {
+                    Ok(frames) if frames > 0 => config.benchmark_frames = frames,
+                    _ => eprintln!("Invalid benchmark frame count: {:?}", value),
+                },
+                _ => eprintln!("Unknown argument: {:?}", arg),
+            }
+        }
+        config
+    }
+}
@@ -0,0 +1,395 @@
+use std::{borrow::Cow, path::PathBuf};
+
+use bytemuck::{Pod, Zeroable};
+use wgpu::{util::DeviceExt, ShaderSource};
+
+use crate::texture::RgbaImage;
+
+/// 星空の後ろに描く背景
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum Background {
+    /// 何も描かない（透明のまま）
+    #[default]
+    Transparent,
+    Solid([f32; 4]),
+    /// 上から下へのグラデーション
+    VerticalGradient { top: [f32; 4], bottom: [f32; 4] },
+    /// 画面の中心から外側へのグラデーション
+    RadialGradient { inner: [f32; 4], outer: [f32; 4] },
+    /// PNG画像を画面を覆うように貼る
+    Image(PathBuf),
+}
+
+impl Background {
+    /// `transparent` `solid:<色>` `vertical:<上の色>,<下の色>` `radial:<中心の色>,<外側の色>` `image:<PNGのパス>`
+    /// 色は `#rrggbb` または `#rrggbbaa`（sRGB）
+    pub fn parse(value: &str) -> Option<Self> {
+        let (kind, args) = value.split_once(':').unwrap_or((value, ""));
+        let colors = || -> Option<Vec<[f32; 4]>> { args.split(',').map(parse_color).collect() };
+        match kind {
+            "transparent" => Some(Background::Transparent),
+            "solid" => Some(Background::Solid(parse_color(args)?)),
+            "vertical" => match colors()?.as_slice() {
+                [top, bottom] => Some(Background::VerticalGradient { top: *top, bottom: *bottom }),
+                _ => None,
+            },
+            "radial" => match colors()?.as_slice() {
+                [inner, outer] => Some(Background::RadialGradient { inner: *inner, outer: *outer }),
+                _ => None,
+            },
+            "image" if !args.is_empty() => Some(Background::Image(PathBuf::from(args))),
+            _ => None,
+        }
+    }
+}
+
+/// `#rrggbb` または `#rrggbbaa` をリニアなRGBAにする
+pub(crate) fn parse_color(value: &str) -> Option<[f32; 4]> {
+    let hex = value.strip_prefix('#').unwrap_or(value);
+    // `from_str_radix` は先頭の `+` を受け付けるので、16進数の数字だけかを先に確かめる
+    if !matches!(hex.len(), 6 | 8) || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).ok().map(|c| c as f32 / 255.0);
+    let srgb_to_linear = |c: f32| if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) };
+    let alpha = if hex.len() == 8 { channel(3)? } else { 1.0 };
+    Some([
+        srgb_to_linear(channel(0)?),
+        srgb_to_linear(channel(1)?),
+        srgb_to_linear(channel(2)?),
+        alpha,
+    ])
+}
+
+/// 背景の設定
+#[derive(Clone, Debug, PartialEq)]
+pub struct BackgroundSettings {
+    pub background: Background,
+    /// 視差で動く小さな星の層の数（0なら描かない、`MAX_PARALLAX_LAYERS` まで）
+    pub parallax_layers: u32,
+    /// カメラの速度（画面の高さを1とした単位/秒）
+    pub camera_velocity: [f32; 2],
+}
+
+impl BackgroundSettings {
+    /// 層の数の上限。シェーダーは画素ごとに全ての層を回る
+    pub const MAX_PARALLAX_LAYERS: u32 = 8;
+}
+
+impl Default for BackgroundSettings {
+    fn default() -> Self {
+        Self {
+            background: Background::Transparent,
+            parallax_layers: 0,
+            camera_velocity: [0.05, 0.0],
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+struct BackgroundUniform {
+    kind: u32,
+    layer_count: u32,
+    time: f32,
+    _padding: f32,
+    color_a: [f32; 4],
+    color_b: [f32; 4],
+    screen_size: [f32; 2],
+    camera: [f32; 2],
+}
+
+/// 背景を画面全体に描くパイプライン
+pub struct BackgroundPipeline {
+    settings: BackgroundSettings,
+    pipeline: wgpu::RenderPipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    uniform_buffer: wgpu::Buffer,
+    sampler: wgpu::Sampler,
+    bind_group: wgpu::BindGroup,
+}
+
+impl BackgroundPipeline {
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+        depth_stencil: Option<wgpu::DepthStencilState>,
+    ) -> Self {
+        let bind_group_layout = device.create_bind_group_layout(
+            &wgpu::BindGroupLayoutDescriptor {
+                label: Some("Background Bind Group Layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None
+                        },
+                        count: None
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None
+                    },
+                ]
+            }
+        );
+
+        let uniform_buffer = device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("Background Uniform Buffer"),
+                contents: bytemuck::cast_slice(&[BackgroundUniform::zeroed()]),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST
+            }
+        );
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Background Sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        // 画像を使わない時のための1x1のテクスチャ
+        let image = RgbaImage::solid([0, 0, 0, 0]).create_texture(device, queue, "Background Image");
+        let bind_group = Self::create_bind_group(device, &bind_group_layout, &uniform_buffer, &image, &sampler);
+
+        Self {
+            settings: BackgroundSettings::default(),
+            pipeline: Self::create_pipeline(device, &bind_group_layout, format, sample_count, depth_stencil),
+            bind_group_layout,
+            uniform_buffer,
+            sampler,
+            bind_group,
+        }
+    }
+
+    fn create_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        uniform_buffer: &wgpu::Buffer,
+        image: &wgpu::Texture,
+        sampler: &wgpu::Sampler,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(
+            &wgpu::BindGroupDescriptor {
+                label: Some("Background Bind Group"),
+                layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: uniform_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::TextureView(
+                            &image.create_view(&wgpu::TextureViewDescriptor::default())
+                        ),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: wgpu::BindingResource::Sampler(sampler),
+                    },
+                ],
+            }
+        )
+    }
+
+    fn create_pipeline(
+        device: &wgpu::Device,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+        depth_stencil: Option<wgpu::DepthStencilState>,
+    ) -> wgpu::RenderPipeline {
+        let pipeline_layout = device.create_pipeline_layout(
+            &wgpu::PipelineLayoutDescriptor {
+                label: Some("Background Pipeline Layout"),
+                bind_group_layouts: &[bind_group_layout],
+                push_constant_ranges: &[]
+            }
+        );
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Background Shader"),
+            source: ShaderSource::Wgsl(Cow::Borrowed(include_str!("../background_shader.wgsl"))),
+        });
+
+        device.create_render_pipeline(
+            &wgpu::RenderPipelineDescriptor {
+                label: Some("Background Pipeline"),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: Some("vertexMain"),
+                    compilation_options: Default::default(),
+                    buffers: &[],
+                },
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil,
+                multisample: wgpu::MultisampleState {
+                    count: sample_count,
+                    ..Default::default()
+                },
+                multiview: None,
+                cache: None,
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: Some("fragmentMain"),
+                    targets: &[Some(wgpu::ColorTargetState {
+                        write_mask: wgpu::ColorWrites::ALL,
+                        format,
+                        blend: Some(wgpu::BlendState::REPLACE),
+                    })],
+                    compilation_options: Default::default(),
+                })
+            }
+        )
+    }
+
+    /// 描画先の形式・MSAAのサンプル数・深度の設定に合わせてパイプラインを作り直す
+    pub fn set_target(
+        &mut self,
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+        depth_stencil: Option<wgpu::DepthStencilState>,
+    ) {
+        self.pipeline = Self::create_pipeline(device, &self.bind_group_layout, format, sample_count, depth_stencil);
+    }
+
+    pub fn settings(&self) -> &BackgroundSettings {
+        &self.settings
+    }
+
+    /// 背景を切り替える
+    /// 画像が読み込めなかった時は透明にし、層の数は上限までに抑える
+    pub fn set_settings(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, mut settings: BackgroundSettings) {
+        if settings.parallax_layers > BackgroundSettings::MAX_PARALLAX_LAYERS {
+            eprintln!(
+                "Parallax layer count {} is larger than {}, clamping",
+                settings.parallax_layers,
+                BackgroundSettings::MAX_PARALLAX_LAYERS
+            );
+            settings.parallax_layers = BackgroundSettings::MAX_PARALLAX_LAYERS;
+        }
+        let image = match &settings.background {
+            Background::Image(path) => match RgbaImage::load_png(path) {
+                Ok(image) => image,
+                Err(err) => {
+                    eprintln!("Failed to load background image {}: {}", path.display(), err);
+                    settings.background = Background::Transparent;
+                    RgbaImage::solid([0, 0, 0, 0])
+                }
+            },
+            _ => RgbaImage::solid([0, 0, 0, 0]),
+        };
+        let texture = image.create_texture(device, queue, "Background Image");
+        self.bind_group = Self::create_bind_group(device, &self.bind_group_layout, &self.uniform_buffer, &texture, &self.sampler);
+        self.settings = settings;
+    }
+
+    /// 何か描くものがあるか
+    pub fn is_visible(&self) -> bool {
+        self.settings.background != Background::Transparent || self.settings.parallax_layers > 0
+    }
+
+    /// 経過時間と描画先の大きさをGPUに送る
+    pub fn write_uniform(&self, queue: &wgpu::Queue, time: f32, screen_size: (u32, u32)) {
+        let (kind, color_a, color_b) = match &self.settings.background {
+            Background::Transparent => (0, [0.0; 4], [0.0; 4]),
+            Background::Solid(color) => (1, *color, *color),
+            Background::VerticalGradient { top, bottom } => (2, *top, *bottom),
+            Background::RadialGradient { inner, outer } => (3, *inner, *outer),
+            Background::Image(_) => (4, [0.0; 4], [0.0; 4]),
+        };
+        let [vx, vy] = self.settings.camera_velocity;
+        let uniform = BackgroundUniform {
+            kind,
+            layer_count: self.settings.parallax_layers.min(BackgroundSettings::MAX_PARALLAX_LAYERS),
+            time,
+            _padding: 0.0,
+            color_a,
+            color_b,
+            screen_size: [screen_size.0 as f32, screen_size.1 as f32],
+            camera: [vx * time, vy * time],
+        };
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniform]));
+    }
+
+    pub fn draw(&self, rpass: &mut wgpu::RenderPass<'_>) {
+        if !self.is_visible() {
+            return;
+        }
+        rpass.set_pipeline(&self.pipeline);
+        rpass.set_bind_group(0, &self.bind_group, &[]);
+        rpass.draw(0..3, 0..1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: [f32; 4], expected: [f32; 4]) {
+        assert!(
+            actual.iter().zip(expected).all(|(a, e)| (a - e).abs() < 1e-4),
+            "{:?} != {:?}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn parses_colors_as_linear_rgba() {
+        assert_close(parse_color("#ffffff").unwrap(), [1.0, 1.0, 1.0, 1.0]);
+        assert_close(parse_color("000000").unwrap(), [0.0, 0.0, 0.0, 1.0]);
+        // sRGB の 0.5 はリニアでは約 0.214、アルファはそのまま
+        assert_close(parse_color("#80808080").unwrap(), [0.2158, 0.2158, 0.2158, 128.0 / 255.0]);
+    }
+
+    #[test]
+    fn rejects_malformed_colors() {
+        for value in ["", "#fff", "#fffffff", "#ggffff", "#+fffff", "#ffffffffff", "#ｆｆｆ"] {
+            assert_eq!(parse_color(value), None, "{:?}", value);
+        }
+    }
+
+    #[test]
+    fn parses_each_background() {
+        assert_eq!(Background::parse("transparent"), Some(Background::Transparent));
+        assert_eq!(Background::parse("solid:#ffffff"), Some(Background::Solid([1.0; 4])));
+        assert_eq!(
+            Background::parse("vertical:#ffffff,#000000"),
+            Some(Background::VerticalGradient { top: [1.0; 4], bottom: [0.0, 0.0, 0.0, 1.0] })
+        );
+        assert_eq!(
+            Background::parse("radial:#000000,#ffffff00"),
+            Some(Background::RadialGradient { inner: [0.0, 0.0, 0.0, 1.0], outer: [1.0, 1.0, 1.0, 0.0] })
+        );
+        assert_eq!(Background::parse("image:sky.png"), Some(Background::Image(PathBuf::from("sky.png"))));
+    }
+
+    #[test]
+    fn rejects_malformed_backgrounds() {
+        for value in ["", "solid", "solid:#fff", "vertical:#ffffff", "radial:#ffffff,#000000,#ffffff", "image:", "sky"] {
+            assert_eq!(Background::parse(value), None, "{:?}", value);
+        }
+    }
+}
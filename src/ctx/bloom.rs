@@ -0,0 +1,364 @@
+use std::borrow::Cow;
+
+use bytemuck::{Pod, Zeroable};
+use wgpu::ShaderSource;
+
+use super::gpu_timer::GpuTimer;
+
+/// ブルームの強さ・しきい値・半径
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BloomSettings {
+    /// 元の描画に足す光の強さ
+    pub intensity: f32,
+    /// これより明るい（輝度）部分だけが光る
+    pub threshold: f32,
+    /// ぼかしの半径（画面のピクセル数、`MAX_RADIUS` まで）
+    pub radius: f32,
+}
+
+/// ぼかしで片側に読むテクセル数の上限（シェーダーの先頭に足して共有する）
+const MAX_BLUR_TAPS: u32 = 32;
+
+impl BloomSettings {
+    /// ぼかしの半径の上限。ぼかしは半分の解像度で行うので、読むテクセル数の2倍になる
+    pub const MAX_RADIUS: f32 = MAX_BLUR_TAPS as f32 * 2.0;
+}
+
+impl Default for BloomSettings {
+    fn default() -> Self {
+        Self {
+            intensity: 1.0,
+            threshold: 0.6,
+            radius: 16.0,
+        }
+    }
+}
+
+/// シェーダーに渡すパスごとの設定
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+struct BloomParams {
+    direction: [f32; 2],
+    threshold: f32,
+    intensity: f32,
+    radius: f32,
+    _padding: [f32; 3],
+}
+
+/// ブルームの各パスで使うパイプラインとバインドグループ
+struct BloomPass {
+    label: &'static str,
+    pipeline: wgpu::RenderPipeline,
+    params_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+}
+
+/// 星空をHDRのテクスチャに描き、明るい部分をぼかして描画先に合成する
+///
+/// 明るい部分の抽出 → 横方向のぼかし → 縦方向のぼかし → 合成 の順に描く。
+/// 抽出とぼかしは半分の解像度で行う。
+pub struct Bloom {
+    pub settings: BloomSettings,
+    bind_group_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    /// 星空を描くHDRのテクスチャ
+    scene_texture: wgpu::Texture,
+    /// 半分の解像度で交互に読み書きするテクスチャ
+    ping_texture: wgpu::Texture,
+    pong_texture: wgpu::Texture,
+    bright_pass: BloomPass,
+    blur_horizontal: BloomPass,
+    blur_vertical: BloomPass,
+    composite: BloomPass,
+}
+
+impl Bloom {
+    /// 星空を描くテクスチャの形式
+    pub const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+    pub fn new(
+        device: &wgpu::Device,
+        target_format: wgpu::TextureFormat,
+        size: (u32, u32),
+        settings: BloomSettings,
+    ) -> Self {
+        if settings.radius > BloomSettings::MAX_RADIUS {
+            eprintln!("Bloom radius {} is larger than {}, clamping", settings.radius, BloomSettings::MAX_RADIUS);
+        }
+        let texture_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None
+        };
+        let bind_group_layout = device.create_bind_group_layout(
+            &wgpu::BindGroupLayoutDescriptor {
+                label: Some("Bloom Bind Group Layout"),
+                entries: &[
+                    texture_entry(0),
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None
+                        },
+                        count: None
+                    },
+                    texture_entry(3),
+                ]
+            }
+        );
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Bloom Sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Bloom Shader"),
+            source: ShaderSource::Wgsl(Cow::Owned(format!(
+                "const MAX_BLUR_TAPS: i32 = {};\n{}",
+                MAX_BLUR_TAPS,
+                include_str!("../bloom_shader.wgsl")
+            ))),
+        });
+        let pipeline_layout = device.create_pipeline_layout(
+            &wgpu::PipelineLayoutDescriptor {
+                label: Some("Bloom Pipeline Layout"),
+                bind_group_layouts: &[&bind_group_layout],
+                push_constant_ranges: &[]
+            }
+        );
+        let create_pipeline = |label, entry_point, format| device.create_render_pipeline(
+            &wgpu::RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: Some("vertexMain"),
+                    compilation_options: Default::default(),
+                    buffers: &[],
+                },
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+                cache: None,
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: Some(entry_point),
+                    targets: &[Some(wgpu::ColorTargetState {
+                        write_mask: wgpu::ColorWrites::ALL,
+                        format,
+                        blend: Some(wgpu::BlendState::REPLACE),
+                    })],
+                    compilation_options: Default::default(),
+                })
+            }
+        );
+
+        let (width, height) = size;
+        let scene_texture = Self::create_texture(device, "Bloom Scene Texture", width, height);
+        let ping_texture = Self::create_texture(device, "Bloom Ping Texture", width / 2, height / 2);
+        let pong_texture = Self::create_texture(device, "Bloom Pong Texture", width / 2, height / 2);
+
+        let create_pass = |label, pipeline, source: &wgpu::Texture, bloom: &wgpu::Texture| {
+            let params_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some(label),
+                size: std::mem::size_of::<BloomParams>() as u64,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+            let bind_group = Self::create_bind_group(device, &bind_group_layout, &sampler, &params_buffer, source, bloom);
+            BloomPass { label, pipeline, params_buffer, bind_group }
+        };
+        let bright_pass = create_pass(
+            "BRIGHT",
+            create_pipeline("Bloom Bright Pass Pipeline", "fragmentBrightPass", Self::FORMAT),
+            &scene_texture,
+            &scene_texture
+        );
+        let blur_horizontal = create_pass(
+            "BLUR H",
+            create_pipeline("Bloom Blur Pipeline", "fragmentBlur", Self::FORMAT),
+            &ping_texture,
+            &ping_texture
+        );
+        let blur_vertical = create_pass(
+            "BLUR V",
+            create_pipeline("Bloom Blur Pipeline", "fragmentBlur", Self::FORMAT),
+            &pong_texture,
+            &pong_texture
+        );
+        let composite = create_pass(
+            "COMPOSITE",
+            create_pipeline("Bloom Composite Pipeline", "fragmentComposite", target_format),
+            &scene_texture,
+            &ping_texture
+        );
+
+        Self {
+            settings,
+            bind_group_layout,
+            sampler,
+            scene_texture,
+            ping_texture,
+            pong_texture,
+            bright_pass,
+            blur_horizontal,
+            blur_vertical,
+            composite,
+        }
+    }
+
+    fn create_texture(device: &wgpu::Device, label: &str, width: u32, height: u32) -> wgpu::Texture {
+        device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d {
+                width: width.max(1),
+                height: height.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: Self::FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        })
+    }
+
+    fn create_bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        sampler: &wgpu::Sampler,
+        params_buffer: &wgpu::Buffer,
+        source: &wgpu::Texture,
+        bloom: &wgpu::Texture,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(
+            &wgpu::BindGroupDescriptor {
+                label: Some("Bloom Bind Group"),
+                layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(
+                            &source.create_view(&wgpu::TextureViewDescriptor::default())
+                        ),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(sampler),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: params_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 3,
+                        resource: wgpu::BindingResource::TextureView(
+                            &bloom.create_view(&wgpu::TextureViewDescriptor::default())
+                        ),
+                    },
+                ],
+            }
+        )
+    }
+
+    /// 描画先の大きさに合わせてテクスチャを作り直す
+    pub fn resize(&mut self, device: &wgpu::Device, size: (u32, u32)) {
+        let (width, height) = size;
+        self.scene_texture = Self::create_texture(device, "Bloom Scene Texture", width, height);
+        self.ping_texture = Self::create_texture(device, "Bloom Ping Texture", width / 2, height / 2);
+        self.pong_texture = Self::create_texture(device, "Bloom Pong Texture", width / 2, height / 2);
+
+        for (pass, source, bloom) in [
+            (&mut self.bright_pass, &self.scene_texture, &self.scene_texture),
+            (&mut self.blur_horizontal, &self.ping_texture, &self.ping_texture),
+            (&mut self.blur_vertical, &self.pong_texture, &self.pong_texture),
+            (&mut self.composite, &self.scene_texture, &self.ping_texture),
+        ] {
+            pass.bind_group = Self::create_bind_group(
+                device,
+                &self.bind_group_layout,
+                &self.sampler,
+                &pass.params_buffer,
+                source,
+                bloom
+            );
+        }
+    }
+
+    /// 星空を描くテクスチャのビュー
+    pub fn scene_view(&self) -> wgpu::TextureView {
+        self.scene_texture.create_view(&wgpu::TextureViewDescriptor::default())
+    }
+
+    /// 設定をGPUに送る
+    pub fn write_settings(&self, queue: &wgpu::Queue) {
+        let params = |direction| BloomParams {
+            direction,
+            threshold: self.settings.threshold,
+            intensity: self.settings.intensity,
+            // ぼかしは半分の解像度で行う
+            radius: self.settings.radius.min(BloomSettings::MAX_RADIUS) * 0.5,
+            _padding: [0.0; 3],
+        };
+        queue.write_buffer(&self.bright_pass.params_buffer, 0, bytemuck::cast_slice(&[params([0.0, 0.0])]));
+        queue.write_buffer(&self.blur_horizontal.params_buffer, 0, bytemuck::cast_slice(&[params([1.0, 0.0])]));
+        queue.write_buffer(&self.blur_vertical.params_buffer, 0, bytemuck::cast_slice(&[params([0.0, 1.0])]));
+        queue.write_buffer(&self.composite.params_buffer, 0, bytemuck::cast_slice(&[params([0.0, 0.0])]));
+    }
+
+    /// 明るい部分を取り出してぼかす
+    pub fn blur(&self, encoder: &mut wgpu::CommandEncoder, mut gpu_timer: Option<&mut GpuTimer>) {
+        for (pass, target) in [
+            (&self.bright_pass, &self.ping_texture),
+            (&self.blur_horizontal, &self.pong_texture),
+            (&self.blur_vertical, &self.ping_texture),
+        ] {
+            let view = target.create_view(&wgpu::TextureViewDescriptor::default());
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some(pass.label),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: gpu_timer.as_deref_mut().and_then(|timer| timer.pass_writes(pass.label)),
+                occlusion_query_set: None,
+            });
+            rpass.set_pipeline(&pass.pipeline);
+            rpass.set_bind_group(0, &pass.bind_group, &[]);
+            rpass.draw(0..3, 0..1);
+        }
+    }
+
+    /// 星空とぼかした光を合成する
+    pub fn composite(&self, rpass: &mut wgpu::RenderPass<'_>) {
+        rpass.set_pipeline(&self.composite.pipeline);
+        rpass.set_bind_group(0, &self.composite.bind_group, &[]);
+        rpass.draw(0..3, 0..1);
+    }
+}
@@ -2,6 +2,7 @@ use std::sync::Arc;
 
 use winit::window::Window;
 
+#[allow(unused, async_fn_in_trait)]
 pub trait WgpuCtxBase {
     fn new(window: Arc<Window>) -> Self;
     async fn new_async(window: Arc<Window>) -> Self;
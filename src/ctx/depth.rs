@@ -0,0 +1,83 @@
+use crate::vertex::instance::StarInstance;
+
+/// 星空を描くパスの深度バッファの形式
+pub const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+
+/// 重なった星をどの順に描くか
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum StarOrdering {
+    /// 奥（depth が大きい）の星から順に並べ替えて描く
+    /// 半透明の星や光彩も正しく重なる
+    #[default]
+    Sorted,
+    /// 並べ替えず、深度バッファで手前の星を残す
+    /// 並べ替えの手間は無いが、半透明の縁は後ろの星を隠す
+    DepthBuffer,
+}
+
+impl StarOrdering {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "sorted" => Some(StarOrdering::Sorted),
+            "buffer" => Some(StarOrdering::DepthBuffer),
+            _ => None,
+        }
+    }
+
+    /// 星を描くパイプラインの深度の設定
+    pub fn depth_stencil(&self) -> wgpu::DepthStencilState {
+        let (depth_write_enabled, depth_compare) = match self {
+            // 並べ替え済みなので深度は比べない
+            StarOrdering::Sorted => (false, wgpu::CompareFunction::Always),
+            StarOrdering::DepthBuffer => (true, wgpu::CompareFunction::LessEqual),
+        };
+        wgpu::DepthStencilState {
+            format: DEPTH_FORMAT,
+            depth_write_enabled,
+            depth_compare,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }
+    }
+
+    /// 必要なら星を奥から手前の順に並べ替える
+    pub fn sort(&self, instances: &mut [StarInstance]) {
+        if *self == StarOrdering::Sorted {
+            instances.sort_by(|a, b| b.depth.total_cmp(&a.depth));
+        }
+    }
+}
+
+/// 3Dに置いた星を描く時の設定
+/// 並べ替えは星の `depth` でしか行えないので、並べ方に関わらず深度バッファで前後を決める
+pub fn test_depth() -> wgpu::DepthStencilState {
+    StarOrdering::DepthBuffer.depth_stencil()
+}
+
+/// 背景やオーバーレイなど、深度バッファのあるパスで深度を無視して描く時の設定
+pub fn ignore_depth() -> wgpu::DepthStencilState {
+    wgpu::DepthStencilState {
+        format: DEPTH_FORMAT,
+        depth_write_enabled: false,
+        depth_compare: wgpu::CompareFunction::Always,
+        stencil: wgpu::StencilState::default(),
+        bias: wgpu::DepthBiasState::default(),
+    }
+}
+
+pub fn create_depth_texture(device: &wgpu::Device, size: (u32, u32), sample_count: u32) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Depth Texture"),
+        size: wgpu::Extent3d {
+            width: size.0.max(1),
+            height: size.1.max(1),
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count,
+        dimension: wgpu::TextureDimension::D2,
+        format: DEPTH_FORMAT,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        view_formats: &[],
+    })
+}
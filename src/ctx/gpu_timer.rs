@@ -0,0 +1,137 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// タイムスタンプクエリでレンダーパスごとのGPU時間を計測する
+/// アダプタが `TIMESTAMP_QUERY` に対応している時だけ作られる
+pub struct GpuTimer {
+    query_set: wgpu::QuerySet,
+    resolve_buffer: wgpu::Buffer,
+    readback_buffer: wgpu::Buffer,
+    /// 1ティックあたりのナノ秒
+    period: f32,
+    /// 今のフレームで計測しているパス
+    passes: Vec<&'static str>,
+    /// 読み出し待ちのパス
+    readback_passes: Vec<&'static str>,
+    /// 読み出し用バッファのマップ結果
+    mapped: Arc<Mutex<Option<Result<(), wgpu::BufferAsyncError>>>>,
+    in_flight: bool,
+}
+
+impl GpuTimer {
+    /// 1フレームで計測できるパスの数
+    const MAX_PASSES: u32 = 8;
+    const BUFFER_SIZE: u64 = Self::MAX_PASSES as u64 * 2 * wgpu::QUERY_SIZE as u64;
+
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Option<Self> {
+        if !device.features().contains(wgpu::Features::TIMESTAMP_QUERY) {
+            return None;
+        }
+
+        let query_set = device.create_query_set(&wgpu::QuerySetDescriptor {
+            label: Some("Timestamp Query Set"),
+            ty: wgpu::QueryType::Timestamp,
+            count: Self::MAX_PASSES * 2,
+        });
+        let resolve_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Timestamp Resolve Buffer"),
+            size: Self::BUFFER_SIZE,
+            usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let readback_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Timestamp Readback Buffer"),
+            size: Self::BUFFER_SIZE,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Some(Self {
+            query_set,
+            resolve_buffer,
+            readback_buffer,
+            period: queue.get_timestamp_period(),
+            passes: Vec::new(),
+            readback_passes: Vec::new(),
+            mapped: Arc::new(Mutex::new(None)),
+            in_flight: false,
+        })
+    }
+
+    /// レンダーパスの開始と終了にタイムスタンプを書き込む設定を返す
+    /// 前のフレームの読み出しが終わっていない間は計測しない
+    pub fn pass_writes(&mut self, label: &'static str) -> Option<wgpu::RenderPassTimestampWrites<'_>> {
+        if self.in_flight || self.passes.len() as u32 >= Self::MAX_PASSES {
+            return None;
+        }
+        let index = self.passes.len() as u32 * 2;
+        self.passes.push(label);
+        Some(wgpu::RenderPassTimestampWrites {
+            query_set: &self.query_set,
+            beginning_of_pass_write_index: Some(index),
+            end_of_pass_write_index: Some(index + 1),
+        })
+    }
+
+    /// 書き込んだタイムスタンプを読み出し用バッファにコピーする
+    pub fn resolve(&self, encoder: &mut wgpu::CommandEncoder) {
+        if self.passes.is_empty() {
+            return;
+        }
+        let count = self.passes.len() as u32 * 2;
+        encoder.resolve_query_set(&self.query_set, 0..count, &self.resolve_buffer, 0);
+        encoder.copy_buffer_to_buffer(
+            &self.resolve_buffer,
+            0,
+            &self.readback_buffer,
+            0,
+            count as u64 * wgpu::QUERY_SIZE as u64,
+        );
+    }
+
+    /// コマンドの提出後に呼び、読み出し用バッファのマップを要求する
+    pub fn after_submit(&mut self) {
+        if self.passes.is_empty() {
+            return;
+        }
+        self.readback_passes = std::mem::take(&mut self.passes);
+        self.in_flight = true;
+        let mapped = Arc::clone(&self.mapped);
+        self.readback_buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                *mapped.lock().unwrap() = Some(result);
+            });
+    }
+
+    /// 読み出しが終わっていればパスごとのGPU時間を返す
+    pub fn collect(&mut self, device: &wgpu::Device) -> Vec<(&'static str, Duration)> {
+        if !self.in_flight {
+            return Vec::new();
+        }
+        device.poll(wgpu::Maintain::Poll);
+        let Some(result) = self.mapped.lock().unwrap().take() else {
+            return Vec::new();
+        };
+        self.in_flight = false;
+        let passes = std::mem::take(&mut self.readback_passes);
+        if result.is_err() {
+            return Vec::new();
+        }
+
+        let durations = {
+            let data = self.readback_buffer.slice(..).get_mapped_range();
+            let timestamps: &[u64] = bytemuck::cast_slice(&data);
+            passes
+                .into_iter()
+                .zip(timestamps.chunks_exact(2))
+                .map(|(label, pair)| {
+                    let ticks = pair[1].saturating_sub(pair[0]);
+                    (label, Duration::from_nanos((ticks as f64 * self.period as f64) as u64))
+                })
+                .collect()
+        };
+        self.readback_buffer.unmap();
+        durations
+    }
+}
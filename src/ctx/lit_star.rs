@@ -0,0 +1,211 @@
+use std::borrow::Cow;
+
+use bytemuck::{Pod, Zeroable};
+use wgpu::{util::DeviceExt, ShaderSource};
+
+use crate::vertex::{instance::StarInstance, mesh::MeshVertex, position::PositionVertex};
+
+/// 平行光源と星の材質
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct LightSettings {
+    /// 光が来る向き（正規化はシェーダーで行う）
+    pub direction: [f32; 3],
+    /// 光が当たらない面の明るさ
+    pub ambient: f32,
+    pub color: [f32; 3],
+    /// ハイライトの鋭さ
+    pub shininess: f32,
+    /// 星の色に掛ける色
+    pub base_color: [f32; 3],
+    /// ハイライトの強さ
+    pub specular: f32,
+}
+
+impl Default for LightSettings {
+    fn default() -> Self {
+        Self {
+            direction: [0.3, 0.5, 0.8],
+            ambient: 0.25,
+            color: [1.0, 1.0, 1.0],
+            shininess: 32.0,
+            // 星の色をそのまま使う
+            base_color: [1.0, 1.0, 1.0],
+            specular: 0.5,
+        }
+    }
+}
+
+/// 押し出した立体の星を3Dに置き、平行光源で陰影をつけて描くパイプライン
+/// カメラはワープでの描画と共有する
+pub struct LitStarPipeline {
+    pipeline: wgpu::RenderPipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    bind_group: wgpu::BindGroup,
+    light_buffer: wgpu::Buffer,
+    mesh_buffer: wgpu::Buffer,
+    mesh_vertex_count: u32,
+    pub light: LightSettings,
+}
+
+impl LitStarPipeline {
+    /// 星の厚さ（外接円の半径を1とした値）
+    const THICKNESS: f32 = 0.2;
+    /// 表と裏の中心の盛り上がり
+    const BEVEL: f32 = 0.25;
+
+    pub fn new(
+        device: &wgpu::Device,
+        time_uniform_buffer: &wgpu::Buffer,
+        camera_buffer: &wgpu::Buffer,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+        depth_stencil: Option<wgpu::DepthStencilState>,
+    ) -> Self {
+        let uniform_entry = |binding, visibility| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None
+            },
+            count: None
+        };
+        let bind_group_layout = device.create_bind_group_layout(
+            &wgpu::BindGroupLayoutDescriptor {
+                label: Some("Lit Star Bind Group Layout"),
+                entries: &[
+                    uniform_entry(0, wgpu::ShaderStages::VERTEX),
+                    uniform_entry(1, wgpu::ShaderStages::VERTEX),
+                    uniform_entry(2, wgpu::ShaderStages::FRAGMENT),
+                ]
+            }
+        );
+
+        let light = LightSettings::default();
+        let light_buffer = device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("Light Buffer"),
+                contents: bytemuck::cast_slice(&[light]),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST
+            }
+        );
+
+        let bind_group = device.create_bind_group(
+            &wgpu::BindGroupDescriptor {
+                label: Some("Lit Star Bind Group"),
+                layout: &bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: time_uniform_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: camera_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: light_buffer.as_entire_binding(),
+                    }
+                ],
+            }
+        );
+
+        let mesh = MeshVertex::new_extruded_star(PositionVertex::STAR_VERTEX_SIZE * 2, Self::THICKNESS, Self::BEVEL);
+
+        Self {
+            pipeline: Self::create_pipeline(device, &bind_group_layout, format, sample_count, depth_stencil),
+            bind_group_layout,
+            bind_group,
+            light_buffer,
+            mesh_buffer: MeshVertex::get_buffer(device, &mesh),
+            mesh_vertex_count: mesh.len() as u32,
+            light,
+        }
+    }
+
+    fn create_pipeline(
+        device: &wgpu::Device,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+        depth_stencil: Option<wgpu::DepthStencilState>,
+    ) -> wgpu::RenderPipeline {
+        let pipeline_layout = device.create_pipeline_layout(
+            &wgpu::PipelineLayoutDescriptor {
+                label: Some("Lit Star Pipeline Layout"),
+                bind_group_layouts: &[bind_group_layout],
+                push_constant_ranges: &[]
+            }
+        );
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Lit Star Shader"),
+            source: ShaderSource::Wgsl(Cow::Borrowed(include_str!("../lit_star_shader.wgsl"))),
+        });
+
+        device.create_render_pipeline(
+            &wgpu::RenderPipelineDescriptor {
+                label: Some("Lit Star Pipeline"),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: Some("vertexMain"),
+                    compilation_options: Default::default(),
+                    buffers: &[
+                        MeshVertex::MESH_VERTEX_LAYOUT,StarInstance::INSTANCE_VERTEX_LAYOUT
+                    ],
+                },
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleList,
+                    front_face: wgpu::FrontFace::Ccw,
+                    cull_mode: Some(wgpu::Face::Back),
+                    ..Default::default()
+                },
+                depth_stencil,
+                multisample: wgpu::MultisampleState {
+                    count: sample_count,
+                    ..Default::default()
+                },
+                multiview: None,
+                cache: None,
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: Some("fragmentMain"),
+                    targets: &[Some(wgpu::ColorTargetState {
+                        write_mask: wgpu::ColorWrites::ALL,
+                        format,
+                        blend: Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+                    })],
+                    compilation_options: Default::default(),
+                })
+            }
+        )
+    }
+
+    /// 描画先の形式・MSAAのサンプル数・深度の設定に合わせてパイプラインを作り直す
+    pub fn set_target(
+        &mut self,
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+        depth_stencil: Option<wgpu::DepthStencilState>,
+    ) {
+        self.pipeline = Self::create_pipeline(device, &self.bind_group_layout, format, sample_count, depth_stencil);
+    }
+
+    /// 光源の設定をGPUに送る
+    pub fn write_light(&self, queue: &wgpu::Queue) {
+        queue.write_buffer(&self.light_buffer, 0, bytemuck::cast_slice(&[self.light]));
+    }
+
+    pub fn draw(&self, rpass: &mut wgpu::RenderPass<'_>, instance_buffer: &wgpu::Buffer, instance_count: u32) {
+        rpass.set_pipeline(&self.pipeline);
+        rpass.set_bind_group(0, &self.bind_group, &[]);
+        rpass.set_vertex_buffer(0, self.mesh_buffer.slice(..));
+        rpass.set_vertex_buffer(1, instance_buffer.slice(..));
+        rpass.draw(0..self.mesh_vertex_count, 0..instance_count);
+    }
+}
@@ -1,3 +1,12 @@
+pub mod background;
+pub mod bloom;
 pub mod ctx_traits;
+pub mod depth;
+pub mod gpu_timer;
+pub mod lit_star;
+pub mod sdf_star;
+pub mod sprite;
+pub mod trails;
+pub mod warp;
 pub mod wgpu_ctx;
 pub mod wgpu_star_ctx;
\ No newline at end of file
@@ -0,0 +1,235 @@
+use std::borrow::Cow;
+
+use bytemuck::{Pod, Zeroable};
+use wgpu::{util::DeviceExt, ShaderSource};
+
+use crate::vertex::instance::StarInstance;
+
+/// 星の描画方法
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum StarRenderMode {
+    /// PositionVertex のメッシュをインスタンス描画する
+    #[default]
+    Mesh,
+    /// 四角形の上で星の符号付き距離関数を評価する
+    Sdf,
+    /// 四角形にアトラスのスプライトを貼る
+    Sprite,
+    /// メッシュを3Dに置き、透視投影で星の中を進むように描く
+    Warp,
+    /// 押し出した立体の星をワープと同じように置き、平行光源で陰影をつけて描く
+    Lit,
+}
+
+impl StarRenderMode {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "mesh" => Some(StarRenderMode::Mesh),
+            "sdf" => Some(StarRenderMode::Sdf),
+            "sprite" => Some(StarRenderMode::Sprite),
+            "warp" => Some(StarRenderMode::Warp),
+            "lit" => Some(StarRenderMode::Lit),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            StarRenderMode::Mesh => "mesh",
+            StarRenderMode::Sdf => "sdf",
+            StarRenderMode::Sprite => "sprite",
+            StarRenderMode::Warp => "warp",
+            StarRenderMode::Lit => "lit",
+        }
+    }
+}
+
+/// SDFで描く星の見た目
+/// 太さや広がりは星の外接円の半径を1とした値
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct SdfStyle {
+    /// 星の色に掛ける色
+    pub fill_color: [f32; 4],
+    pub outline_color: [f32; 4],
+    pub outline_width: f32,
+    pub glow_radius: f32,
+    pub glow_strength: f32,
+    _padding: f32,
+}
+
+impl Default for SdfStyle {
+    fn default() -> Self {
+        Self {
+            // 星の色をそのまま使う
+            fill_color: [1.0, 1.0, 1.0, 1.0],
+            outline_color: [1.0, 1.0, 1.0, 1.0],
+            outline_width: 0.0,
+            glow_radius: 0.15,
+            glow_strength: 0.0,
+            _padding: 0.0,
+        }
+    }
+}
+
+/// 星を四角形とSDFで描くパイプライン
+pub struct SdfStarPipeline {
+    pipeline: wgpu::RenderPipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    bind_group: wgpu::BindGroup,
+    style_buffer: wgpu::Buffer,
+    format: wgpu::TextureFormat,
+    pub style: SdfStyle,
+}
+
+impl SdfStarPipeline {
+    pub fn new(
+        device: &wgpu::Device,
+        time_uniform_buffer: &wgpu::Buffer,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+        depth_stencil: Option<wgpu::DepthStencilState>,
+    ) -> Self {
+        let bind_group_layout = device.create_bind_group_layout(
+            &wgpu::BindGroupLayoutDescriptor {
+                label: Some("SDF Star Bind Group Layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::VERTEX,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None
+                        },
+                        count: None
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None
+                        },
+                        count: None
+                    }
+                ]
+            }
+        );
+
+        let style = SdfStyle::default();
+        let style_buffer = device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("SDF Style Buffer"),
+                contents: bytemuck::cast_slice(&[style]),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST
+            }
+        );
+
+        let bind_group = device.create_bind_group(
+            &wgpu::BindGroupDescriptor {
+                label: Some("SDF Star Bind Group"),
+                layout: &bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: time_uniform_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: style_buffer.as_entire_binding(),
+                    }
+                ],
+            }
+        );
+
+        Self {
+            pipeline: Self::create_pipeline(device, &bind_group_layout, format, sample_count, depth_stencil),
+            bind_group_layout,
+            bind_group,
+            style_buffer,
+            format,
+            style,
+        }
+    }
+
+    fn create_pipeline(
+        device: &wgpu::Device,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+        depth_stencil: Option<wgpu::DepthStencilState>,
+    ) -> wgpu::RenderPipeline {
+        let pipeline_layout = device.create_pipeline_layout(
+            &wgpu::PipelineLayoutDescriptor {
+                label: Some("SDF Star Pipeline Layout"),
+                bind_group_layouts: &[bind_group_layout],
+                push_constant_ranges: &[]
+            }
+        );
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("SDF Star Shader"),
+            source: ShaderSource::Wgsl(Cow::Borrowed(include_str!("../sdf_star_shader.wgsl"))),
+        });
+
+        device.create_render_pipeline(
+            &wgpu::RenderPipelineDescriptor {
+                label: Some("SDF Star Pipeline"),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: Some("vertexMain"),
+                    compilation_options: Default::default(),
+                    buffers: &[StarInstance::INSTANCE_VERTEX_LAYOUT],
+                },
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleStrip,
+                    ..Default::default()
+                },
+                depth_stencil,
+                multisample: wgpu::MultisampleState {
+                    count: sample_count,
+                    ..Default::default()
+                },
+                multiview: None,
+                cache: None,
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: Some("fragmentMain"),
+                    targets: &[Some(wgpu::ColorTargetState {
+                        write_mask: wgpu::ColorWrites::ALL,
+                        format,
+                        blend: Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+                    })],
+                    compilation_options: Default::default(),
+                })
+            }
+        )
+    }
+
+    /// 描画先の形式・MSAAのサンプル数・深度の設定に合わせてパイプラインを作り直す
+    pub fn set_target(
+        &mut self,
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+        depth_stencil: Option<wgpu::DepthStencilState>,
+    ) {
+        self.format = format;
+        self.pipeline = Self::create_pipeline(device, &self.bind_group_layout, format, sample_count, depth_stencil);
+    }
+
+    /// 見た目の設定をGPUに送る
+    pub fn write_style(&self, queue: &wgpu::Queue) {
+        queue.write_buffer(&self.style_buffer, 0, bytemuck::cast_slice(&[self.style]));
+    }
+
+    pub fn draw(&self, rpass: &mut wgpu::RenderPass<'_>, instance_buffer: &wgpu::Buffer, instance_count: u32) {
+        rpass.set_pipeline(&self.pipeline);
+        rpass.set_bind_group(0, &self.bind_group, &[]);
+        rpass.set_vertex_buffer(0, instance_buffer.slice(..));
+        rpass.draw(0..4, 0..instance_count);
+    }
+}
@@ -0,0 +1,245 @@
+use std::{borrow::Cow, path::Path};
+
+use wgpu::{util::DeviceExt, ShaderSource};
+
+use crate::{texture::RgbaImage, vertex::instance::StarInstance};
+
+/// 同じ大きさのセルを格子状に並べたスプライトの画像
+#[derive(Clone, Debug)]
+pub struct SpriteAtlas {
+    pub image: RgbaImage,
+    pub columns: u32,
+    pub rows: u32,
+}
+
+impl SpriteAtlas {
+    /// PNGファイルを読み込み、列数×行数のセルに分ける
+    pub fn load(path: &Path, columns: u32, rows: u32) -> Result<Self, png::DecodingError> {
+        Ok(Self {
+            image: RgbaImage::load_png(path)?,
+            columns: columns.max(1),
+            rows: rows.max(1),
+        })
+    }
+}
+
+/// 星をアトラスのスプライトを貼った四角形で描くパイプライン
+pub struct SpritePipeline {
+    pipeline: wgpu::RenderPipeline,
+    time_bind_group_layout: wgpu::BindGroupLayout,
+    time_bind_group: wgpu::BindGroup,
+    atlas_bind_group_layout: wgpu::BindGroupLayout,
+    atlas_bind_group: wgpu::BindGroup,
+}
+
+impl SpritePipeline {
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        time_uniform_buffer: &wgpu::Buffer,
+        atlas: &SpriteAtlas,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+        depth_stencil: Option<wgpu::DepthStencilState>,
+    ) -> Self {
+        let time_bind_group_layout = device.create_bind_group_layout(
+            &wgpu::BindGroupLayoutDescriptor {
+                label: Some("Sprite Time Bind Group Layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::VERTEX,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None
+                        },
+                        count: None
+                    }
+                ]
+            }
+        );
+        let time_bind_group = device.create_bind_group(
+            &wgpu::BindGroupDescriptor {
+                label: Some("Sprite Time Bind Group"),
+                layout: &time_bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: time_uniform_buffer.as_entire_binding(),
+                    }
+                ],
+            }
+        );
+
+        let atlas_bind_group_layout = device.create_bind_group_layout(
+            &wgpu::BindGroupLayoutDescriptor {
+                label: Some("Sprite Atlas Bind Group Layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::VERTEX,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None
+                        },
+                        count: None
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None
+                    },
+                ]
+            }
+        );
+
+        let grid_buffer = device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("Sprite Atlas Grid Buffer"),
+                contents: bytemuck::cast_slice(&[atlas.columns, atlas.rows, atlas.image.width, atlas.image.height]),
+                usage: wgpu::BufferUsages::UNIFORM
+            }
+        );
+        let texture = atlas.image.create_texture(device, queue, "Sprite Atlas");
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Sprite Atlas Sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        let atlas_bind_group = device.create_bind_group(
+            &wgpu::BindGroupDescriptor {
+                label: Some("Sprite Atlas Bind Group"),
+                layout: &atlas_bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: grid_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::TextureView(
+                            &texture.create_view(&wgpu::TextureViewDescriptor::default())
+                        ),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: wgpu::BindingResource::Sampler(&sampler),
+                    },
+                ],
+            }
+        );
+
+        Self {
+            pipeline: Self::create_pipeline(
+                device,
+                &time_bind_group_layout,
+                &atlas_bind_group_layout,
+                format,
+                sample_count,
+                depth_stencil
+            ),
+            time_bind_group_layout,
+            time_bind_group,
+            atlas_bind_group_layout,
+            atlas_bind_group,
+        }
+    }
+
+    fn create_pipeline(
+        device: &wgpu::Device,
+        time_bind_group_layout: &wgpu::BindGroupLayout,
+        atlas_bind_group_layout: &wgpu::BindGroupLayout,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+        depth_stencil: Option<wgpu::DepthStencilState>,
+    ) -> wgpu::RenderPipeline {
+        let pipeline_layout = device.create_pipeline_layout(
+            &wgpu::PipelineLayoutDescriptor {
+                label: Some("Sprite Pipeline Layout"),
+                bind_group_layouts: &[time_bind_group_layout, atlas_bind_group_layout],
+                push_constant_ranges: &[]
+            }
+        );
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Sprite Shader"),
+            source: ShaderSource::Wgsl(Cow::Borrowed(include_str!("../sprite_shader.wgsl"))),
+        });
+
+        device.create_render_pipeline(
+            &wgpu::RenderPipelineDescriptor {
+                label: Some("Sprite Pipeline"),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: Some("vertexMain"),
+                    compilation_options: Default::default(),
+                    buffers: &[StarInstance::INSTANCE_VERTEX_LAYOUT],
+                },
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleStrip,
+                    ..Default::default()
+                },
+                depth_stencil,
+                multisample: wgpu::MultisampleState {
+                    count: sample_count,
+                    ..Default::default()
+                },
+                multiview: None,
+                cache: None,
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: Some("fragmentMain"),
+                    targets: &[Some(wgpu::ColorTargetState {
+                        write_mask: wgpu::ColorWrites::ALL,
+                        format,
+                        blend: Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+                    })],
+                    compilation_options: Default::default(),
+                })
+            }
+        )
+    }
+
+    /// 描画先の形式・MSAAのサンプル数・深度の設定に合わせてパイプラインを作り直す
+    pub fn set_target(
+        &mut self,
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+        depth_stencil: Option<wgpu::DepthStencilState>,
+    ) {
+        self.pipeline = Self::create_pipeline(
+            device,
+            &self.time_bind_group_layout,
+            &self.atlas_bind_group_layout,
+            format,
+            sample_count,
+            depth_stencil
+        );
+    }
+
+    pub fn draw(&self, rpass: &mut wgpu::RenderPass<'_>, instance_buffer: &wgpu::Buffer, instance_count: u32) {
+        rpass.set_pipeline(&self.pipeline);
+        rpass.set_bind_group(0, &self.time_bind_group, &[]);
+        rpass.set_bind_group(1, &self.atlas_bind_group, &[]);
+        rpass.set_vertex_buffer(0, instance_buffer.slice(..));
+        rpass.draw(0..4, 0..instance_count);
+    }
+}
@@ -0,0 +1,284 @@
+use std::borrow::Cow;
+
+use bytemuck::{Pod, Zeroable};
+use wgpu::{util::DeviceExt, ShaderSource};
+
+use super::gpu_timer::GpuTimer;
+
+/// 軌跡の長さと減り方
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TrailSettings {
+    /// 軌跡が完全に消えるまでの秒数
+    pub length: f32,
+    /// 1秒後に残る明るさの割合（0.0〜1.0）
+    pub decay: f32,
+}
+
+impl Default for TrailSettings {
+    fn default() -> Self {
+        Self {
+            length: 1.0,
+            decay: 0.1,
+        }
+    }
+}
+
+impl TrailSettings {
+    /// 経過時間 dt 秒の間に、前のフレームまでの軌跡に掛ける割合と引く量
+    fn fade(&self, dt: f32) -> (f32, f32) {
+        (self.decay.clamp(0.0, 1.0).powf(dt), dt / self.length.max(1e-3))
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+struct TrailParams {
+    fade: f32,
+    subtract: f32,
+    _padding: [f32; 2],
+}
+
+/// 星の動いた跡を残す
+///
+/// 星空はいったん `frame_texture` に描き、前のフレームまでの軌跡を薄くしたものと重ねて
+/// 後段に渡す。重ねた結果は次のフレームのために `history_textures` に残す。
+pub struct Trails {
+    pub settings: TrailSettings,
+    pipeline: wgpu::RenderPipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    params_buffer: wgpu::Buffer,
+    /// 今のフレームの星空
+    frame_texture: wgpu::Texture,
+    /// 交互に読み書きする軌跡
+    history_textures: [wgpu::Texture; 2],
+    /// `history_textures[i]` を読む時のバインドグループ
+    bind_groups: [wgpu::BindGroup; 2],
+    /// 次に読む軌跡
+    current: usize,
+}
+
+impl Trails {
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        size: (u32, u32),
+        settings: TrailSettings,
+    ) -> Self {
+        let texture_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None
+        };
+        let bind_group_layout = device.create_bind_group_layout(
+            &wgpu::BindGroupLayoutDescriptor {
+                label: Some("Trails Bind Group Layout"),
+                entries: &[
+                    texture_entry(0),
+                    texture_entry(1),
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None
+                        },
+                        count: None
+                    },
+                ]
+            }
+        );
+
+        let pipeline_layout = device.create_pipeline_layout(
+            &wgpu::PipelineLayoutDescriptor {
+                label: Some("Trails Pipeline Layout"),
+                bind_group_layouts: &[&bind_group_layout],
+                push_constant_ranges: &[]
+            }
+        );
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Trails Shader"),
+            source: ShaderSource::Wgsl(Cow::Borrowed(include_str!("../trails_shader.wgsl"))),
+        });
+        let target = |blend| Some(wgpu::ColorTargetState {
+            write_mask: wgpu::ColorWrites::ALL,
+            format,
+            blend: Some(blend),
+        });
+        let pipeline = device.create_render_pipeline(
+            &wgpu::RenderPipelineDescriptor {
+                label: Some("Trails Pipeline"),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: Some("vertexMain"),
+                    compilation_options: Default::default(),
+                    buffers: &[],
+                },
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+                cache: None,
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: Some("fragmentMain"),
+                    // 後段には背景の上に重ね、軌跡はそのまま残す
+                    targets: &[
+                        target(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+                        target(wgpu::BlendState::REPLACE),
+                    ],
+                    compilation_options: Default::default(),
+                })
+            }
+        );
+
+        let params_buffer = device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("Trails Params Buffer"),
+                contents: bytemuck::cast_slice(&[TrailParams::zeroed()]),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST
+            }
+        );
+
+        let (frame_texture, history_textures, bind_groups) =
+            Self::create_textures(device, &bind_group_layout, &params_buffer, format, size);
+
+        Self {
+            settings,
+            pipeline,
+            bind_group_layout,
+            params_buffer,
+            frame_texture,
+            history_textures,
+            bind_groups,
+            current: 0,
+        }
+    }
+
+    fn create_textures(
+        device: &wgpu::Device,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        params_buffer: &wgpu::Buffer,
+        format: wgpu::TextureFormat,
+        size: (u32, u32),
+    ) -> (wgpu::Texture, [wgpu::Texture; 2], [wgpu::BindGroup; 2]) {
+        let (width, height) = size;
+        let create_texture = |label| device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d {
+                width: width.max(1),
+                height: height.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let frame_texture = create_texture("Trails Frame Texture");
+        let history_textures = [
+            create_texture("Trails History Texture 0"),
+            create_texture("Trails History Texture 1"),
+        ];
+
+        let frame_view = frame_texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let bind_groups = history_textures.each_ref().map(|history| device.create_bind_group(
+            &wgpu::BindGroupDescriptor {
+                label: Some("Trails Bind Group"),
+                layout: bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&frame_view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::TextureView(
+                            &history.create_view(&wgpu::TextureViewDescriptor::default())
+                        ),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: params_buffer.as_entire_binding(),
+                    },
+                ],
+            }
+        ));
+
+        (frame_texture, history_textures, bind_groups)
+    }
+
+    /// 描画先の大きさに合わせてテクスチャを作り直す（軌跡は消える）
+    pub fn resize(&mut self, device: &wgpu::Device, size: (u32, u32)) {
+        let (frame_texture, history_textures, bind_groups) = Self::create_textures(
+            device,
+            &self.bind_group_layout,
+            &self.params_buffer,
+            self.frame_texture.format(),
+            size
+        );
+        self.frame_texture = frame_texture;
+        self.history_textures = history_textures;
+        self.bind_groups = bind_groups;
+        self.current = 0;
+    }
+
+    /// 今のフレームの星空を描くテクスチャのビュー
+    pub fn frame_view(&self) -> wgpu::TextureView {
+        self.frame_texture.create_view(&wgpu::TextureViewDescriptor::default())
+    }
+
+    /// 前のフレームから dt 秒経った時の薄め方をGPUに送る
+    pub fn write_settings(&self, queue: &wgpu::Queue, dt: f32) {
+        let (fade, subtract) = self.settings.fade(dt);
+        queue.write_buffer(
+            &self.params_buffer,
+            0,
+            bytemuck::cast_slice(&[TrailParams { fade, subtract, _padding: [0.0; 2] }])
+        );
+    }
+
+    /// 軌跡と今のフレームを重ねて `target` に描き、次のフレームのために残す
+    /// `target` には先に背景を描いておく
+    pub fn draw(
+        &mut self,
+        encoder: &mut wgpu::CommandEncoder,
+        target: &wgpu::TextureView,
+        gpu_timer: Option<&mut GpuTimer>,
+    ) {
+        let next = 1 - self.current;
+        let history_view = self.history_textures[next].create_view(&wgpu::TextureViewDescriptor::default());
+        let attachment = |view, load| Some(wgpu::RenderPassColorAttachment {
+            view,
+            resolve_target: None,
+            ops: wgpu::Operations {
+                load,
+                store: wgpu::StoreOp::Store,
+            },
+        });
+        {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Trails"),
+                color_attachments: &[
+                    attachment(target, wgpu::LoadOp::Load),
+                    attachment(&history_view, wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT)),
+                ],
+                depth_stencil_attachment: None,
+                timestamp_writes: gpu_timer.and_then(|timer| timer.pass_writes("TRAILS")),
+                occlusion_query_set: None,
+            });
+            rpass.set_pipeline(&self.pipeline);
+            rpass.set_bind_group(0, &self.bind_groups[self.current], &[]);
+            rpass.draw(0..3, 0..1);
+        }
+        self.current = next;
+    }
+}
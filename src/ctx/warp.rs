@@ -0,0 +1,227 @@
+use std::borrow::Cow;
+
+use bytemuck::{Pod, Zeroable};
+use wgpu::{util::DeviceExt, ShaderSource};
+
+use crate::vertex::{instance::StarInstance, position::PositionVertex};
+
+/// 3Dで星の中を進む時のカメラ
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CameraSettings {
+    /// 縦方向の視野角（度）
+    pub fov_y: f32,
+    /// 星が通り過ぎる手前の距離
+    pub near: f32,
+    /// 星が現れる奥の距離
+    pub far: f32,
+    /// 画面上の -1.0..1.0 を3Dの空間でどれだけの広さに置くか
+    pub spread: f32,
+}
+
+impl Default for CameraSettings {
+    fn default() -> Self {
+        Self {
+            fov_y: 60.0,
+            near: 0.1,
+            far: 10.0,
+            spread: 2.0,
+        }
+    }
+}
+
+impl CameraSettings {
+    /// 原点から -z 方向を見る透視投影の行列（列優先、深度は 0.0..1.0）
+    fn view_projection(&self, aspect: f32) -> [[f32; 4]; 4] {
+        let f = 1.0 / (self.fov_y.to_radians() * 0.5).tan();
+        let (near, far) = (self.near, self.far.max(self.near + 1e-3));
+        [
+            [f / aspect, 0.0, 0.0, 0.0],
+            [0.0, f, 0.0, 0.0],
+            [0.0, 0.0, far / (near - far), -1.0],
+            [0.0, 0.0, near * far / (near - far), 0.0],
+        ]
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+struct CameraUniform {
+    view_projection: [[f32; 4]; 4],
+    near: f32,
+    far: f32,
+    spread: f32,
+    _padding: f32,
+}
+
+/// 星のメッシュを3Dに置き、透視投影で描くパイプライン
+/// 星どうしの重なりを正しくするには深度バッファ（`StarOrdering::DepthBuffer`）を使う
+pub struct WarpPipeline {
+    pipeline: wgpu::RenderPipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    bind_group: wgpu::BindGroup,
+    camera_buffer: wgpu::Buffer,
+    pub camera: CameraSettings,
+}
+
+impl WarpPipeline {
+    pub fn new(
+        device: &wgpu::Device,
+        time_uniform_buffer: &wgpu::Buffer,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+        depth_stencil: Option<wgpu::DepthStencilState>,
+    ) -> Self {
+        let uniform_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::VERTEX,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None
+            },
+            count: None
+        };
+        let bind_group_layout = device.create_bind_group_layout(
+            &wgpu::BindGroupLayoutDescriptor {
+                label: Some("Warp Star Bind Group Layout"),
+                entries: &[uniform_entry(0), uniform_entry(1)]
+            }
+        );
+
+        let camera_buffer = device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("Warp Camera Buffer"),
+                contents: bytemuck::cast_slice(&[CameraUniform::zeroed()]),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST
+            }
+        );
+
+        let bind_group = device.create_bind_group(
+            &wgpu::BindGroupDescriptor {
+                label: Some("Warp Star Bind Group"),
+                layout: &bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: time_uniform_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: camera_buffer.as_entire_binding(),
+                    }
+                ],
+            }
+        );
+
+        Self {
+            pipeline: Self::create_pipeline(device, &bind_group_layout, format, sample_count, depth_stencil),
+            bind_group_layout,
+            bind_group,
+            camera_buffer,
+            camera: CameraSettings::default(),
+        }
+    }
+
+    fn create_pipeline(
+        device: &wgpu::Device,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+        depth_stencil: Option<wgpu::DepthStencilState>,
+    ) -> wgpu::RenderPipeline {
+        let pipeline_layout = device.create_pipeline_layout(
+            &wgpu::PipelineLayoutDescriptor {
+                label: Some("Warp Star Pipeline Layout"),
+                bind_group_layouts: &[bind_group_layout],
+                push_constant_ranges: &[]
+            }
+        );
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Warp Star Shader"),
+            source: ShaderSource::Wgsl(Cow::Borrowed(include_str!("../warp_star_shader.wgsl"))),
+        });
+
+        device.create_render_pipeline(
+            &wgpu::RenderPipelineDescriptor {
+                label: Some("Warp Star Pipeline"),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: Some("vertexMain"),
+                    compilation_options: Default::default(),
+                    buffers: &[
+                        PositionVertex::POSITION_VERTEX_LAYOUT,StarInstance::INSTANCE_VERTEX_LAYOUT
+                    ],
+                },
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleStrip,
+                    // 3Dで回すと裏側も見えるので、裏面も描く
+                    cull_mode: None,
+                    ..Default::default()
+                },
+                depth_stencil,
+                multisample: wgpu::MultisampleState {
+                    count: sample_count,
+                    ..Default::default()
+                },
+                multiview: None,
+                cache: None,
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: Some("fragmentMain"),
+                    targets: &[Some(wgpu::ColorTargetState {
+                        write_mask: wgpu::ColorWrites::ALL,
+                        format,
+                        blend: Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+                    })],
+                    compilation_options: Default::default(),
+                })
+            }
+        )
+    }
+
+    /// 描画先の形式・MSAAのサンプル数・深度の設定に合わせてパイプラインを作り直す
+    pub fn set_target(
+        &mut self,
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+        depth_stencil: Option<wgpu::DepthStencilState>,
+    ) {
+        self.pipeline = Self::create_pipeline(device, &self.bind_group_layout, format, sample_count, depth_stencil);
+    }
+
+    /// カメラのユニフォームバッファ（立体の星の描画と共有する）
+    pub fn camera_buffer(&self) -> &wgpu::Buffer {
+        &self.camera_buffer
+    }
+
+    /// 描画先の縦横比に合わせたカメラをGPUに送る
+    pub fn write_camera(&self, queue: &wgpu::Queue, screen_size: (u32, u32)) {
+        let aspect = screen_size.0.max(1) as f32 / screen_size.1.max(1) as f32;
+        let uniform = CameraUniform {
+            view_projection: self.camera.view_projection(aspect),
+            near: self.camera.near,
+            far: self.camera.far,
+            spread: self.camera.spread,
+            _padding: 0.0,
+        };
+        queue.write_buffer(&self.camera_buffer, 0, bytemuck::cast_slice(&[uniform]));
+    }
+
+    pub fn draw(
+        &self,
+        rpass: &mut wgpu::RenderPass<'_>,
+        vertex_buffer: &wgpu::Buffer,
+        vertex_count: u32,
+        instance_buffer: &wgpu::Buffer,
+        instance_count: u32,
+    ) {
+        rpass.set_pipeline(&self.pipeline);
+        rpass.set_bind_group(0, &self.bind_group, &[]);
+        rpass.set_vertex_buffer(0, vertex_buffer.slice(..));
+        rpass.set_vertex_buffer(1, instance_buffer.slice(..));
+        rpass.draw(0..vertex_count, 0..instance_count);
+    }
+}
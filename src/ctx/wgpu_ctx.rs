@@ -1,7 +1,6 @@
 use std::{borrow::Cow, sync::Arc};
-use wgpu::{util::DeviceExt, ShaderSource};
+use wgpu::ShaderSource;
 use winit::window::Window;
-use rand::Rng;
 
 pub struct WgpuCtx<'window> {
     pub surface: wgpu::Surface<'window>,
@@ -1,17 +1,97 @@
-use std::{borrow::Cow, sync::Arc};
-use wgpu::{util::DeviceExt, ShaderSource};
+use std::{borrow::Cow, path::{Path, PathBuf}, sync::{Arc, Mutex}, time::{Duration, Instant}};
+use wgpu::ShaderSource;
 use winit::window::Window;
 
-use crate::{time_uniform::TimeUniform, vertex::{instance::StarInstance, position::PositionVertex}};
+use super::{background::{BackgroundPipeline, BackgroundSettings}, bloom::{Bloom, BloomSettings}, depth::{self, StarOrdering}, gpu_timer::GpuTimer, lit_star::LitStarPipeline, sdf_star::{SdfStarPipeline, StarRenderMode}, sprite::{SpriteAtlas, SpritePipeline}, trails::{TrailSettings, Trails}, warp::WarpPipeline};
+use crate::{boids::{self, BoidsSettings}, clock::Clock, collision, curves::LifetimeCurves, emitter::{EmitterSystem, StarLife}, fireworks::Fireworks, forces::{self, ForceField}, interaction::{self, InteractionSettings, View}, overlay::{stats::FrameStats, StatsOverlay}, simulation::{self, EdgeMode, SimulationMode}, spatial::SpatialGrid, texture::RgbaImage, time_uniform::TimeUniform, vertex::{instance::StarInstance, position::PositionVertex}};
+
+/// 描画先
+pub enum RenderTarget<'window> {
+    /// ウィンドウのサーフェス
+    Surface(wgpu::Surface<'window>),
+    /// ヘッドレス描画用のテクスチャ
+    Offscreen(wgpu::Texture),
+}
 
 pub struct WgpuStarCtx<'window> {
-    pub surface: wgpu::Surface<'window>,
+    pub target: RenderTarget<'window>,
     pub device: wgpu::Device,
     pub queue: wgpu::Queue,
     pub surface_config: wgpu::SurfaceConfiguration,
     pub render_pipeline: wgpu::RenderPipeline,
-    #[allow(unused)]
-    pub adapter: wgpu::Adapter
+    uniform_bind_group_layout: wgpu::BindGroupLayout,
+    /// 星をメッシュとSDFのどちらで描くか
+    pub render_mode: StarRenderMode,
+    /// SDFで星を描くパイプライン
+    pub sdf: SdfStarPipeline,
+    /// アトラスのスプライトで星を描くパイプライン（アトラスを読み込むまでは無い）
+    pub sprites: Option<SpritePipeline>,
+    /// 3Dに置いた星を透視投影で描くパイプライン
+    pub warp: WarpPipeline,
+    /// 押し出した立体の星を陰影をつけて描くパイプライン
+    pub lit: LitStarPipeline,
+    /// MSAAのサンプル数（1ならMSAAなし）
+    pub sample_count: u32,
+    /// MSAA用のマルチサンプルテクスチャ（描画後に描画先へ解決する）
+    msaa_texture: Option<wgpu::Texture>,
+    /// 重なった星をどの順に描くか
+    pub ordering: StarOrdering,
+    /// 星空を描くパスの深度バッファ（サンプル数は `sample_count` に合わせる）
+    depth_texture: wgpu::Texture,
+    /// 星空の後ろに描く背景
+    pub background: BackgroundPipeline,
+    /// ブルーム（有効な時は星空をHDRのテクスチャに描いてから合成する）
+    pub bloom: Option<Bloom>,
+    /// 星の軌跡（有効な時は星空を前のフレームまでの軌跡と重ねる）
+    pub trails: Option<Trails>,
+    pub adapter: wgpu::Adapter,
+    pub vertex_buffer: wgpu::Buffer,
+    pub vertex_count: u32,
+    pub instances: Vec<StarInstance>,
+    pub instance_buffer: wgpu::Buffer,
+    pub instance_count: u32,
+    /// 星の動きをCPUとGPUのどちらで計算するか
+    pub simulation: SimulationMode,
+    /// 最後にCPUで星を動かした時刻
+    last_step: f32,
+    /// 描画先の端での振る舞い（CPUで動かす時だけ）
+    pub edges: EdgeMode,
+    /// 星どうしを衝突させる（CPUで動かす時だけ）
+    pub collisions: bool,
+    /// 星を加速する力の場（CPUで動かす時だけ）
+    pub forces: Vec<ForceField>,
+    /// 群れで飛ぶ（`None` なら使わない。CPUで動かす時だけ）
+    pub boids: Option<BoidsSettings>,
+    /// 星を生み出すエミッター（`None` なら決まった数の星が消えずに残る。CPUで動かす時だけ）
+    pub emitters: Option<EmitterSystem>,
+    /// 花火（`None` なら使わない。CPUで動かす時だけ）
+    pub fireworks: Option<Fireworks>,
+    /// 寿命に合わせて星の見た目と回転を変える曲線（`None` なら使わない。CPUで動かす時だけ）
+    pub curves: Option<LifetimeCurves>,
+    /// 衝突と群れの判定で使い回すマス目
+    grid: SpatialGrid,
+    /// 星空の座標でのカーソルの位置（描画先の外なら `None`）
+    pub pointer: Option<[f32; 2]>,
+    /// 星空の座標での触れている指の位置（カーソルと同じ力を及ぼす）
+    pub touch_points: Vec<[f32; 2]>,
+    /// 星空の表示の拡大とずらし
+    pub view: View,
+    /// マウスでの操作の設定
+    pub interaction: InteractionSettings,
+    pub uniform_buffer: wgpu::Buffer,
+    pub uniform_bind_group: wgpu::BindGroup,
+    time_uniform: TimeUniform,
+    /// 統計情報のオーバーレイ
+    pub overlay: StatsOverlay,
+    pub stats: FrameStats,
+    /// タイムスタンプクエリによるGPU時間の計測
+    gpu_timer: Option<GpuTimer>,
+    /// 提出したコマンドの完了までの時間（タイムスタンプクエリが使えない時のGPU時間の概算）
+    gpu_done: Arc<Mutex<Option<Duration>>>,
+    /// アニメーションの時計
+    pub clock: Clock,
+    /// 次に描いたフレームを保存するPNGのパス
+    screenshot: Option<PathBuf>,
 }
 
 
@@ -40,10 +120,85 @@ impl<'window> WgpuStarCtx<'window> {
                 compatible_surface: Some(&surface)
             }
         ).await.unwrap();
+        let (device, queue) = Self::request_device(&adapter).await;
+
+        let size = window.inner_size();
+        let (width,height) = (size.width.max(1),size.width.max(1));
+        let mut surface_config = surface
+            .get_default_config(&adapter, width, height)
+            .unwrap();
+        // 対応していればスクリーンショットのためにサーフェスから読み出せるようにする
+        if surface.get_capabilities(&adapter).usages.contains(wgpu::TextureUsages::COPY_SRC) {
+            surface_config.usage |= wgpu::TextureUsages::COPY_SRC;
+        }
+        surface.configure(&device, &surface_config);
+
+        Self::with_target(RenderTarget::Surface(surface), adapter, device, queue, surface_config)
+    }
+
+    /// ウィンドウを使わずにテクスチャへ描画するコンテキストを作る
+    pub fn new_headless(width: u32, height: u32) -> Self {
+        pollster::block_on(WgpuStarCtx::new_headless_async(width, height, false))
+    }
+
+    /// ソフトウェアのアダプタでテクスチャへ描画するコンテキストを作る
+    /// GPUによって描画結果が変わらないので、参照画像との比較に使う
+    pub fn new_software(width: u32, height: u32) -> Self {
+        pollster::block_on(WgpuStarCtx::new_headless_async(width, height, true))
+    }
+
+    async fn new_headless_async(width: u32, height: u32, force_fallback_adapter: bool) -> Self {
+        let instance = wgpu::Instance::default();
+        let adapter = instance.request_adapter(
+            &wgpu::RequestAdapterOptionsBase {
+                power_preference: wgpu::PowerPreference::default(),
+                force_fallback_adapter,
+                compatible_surface: None
+            }
+        ).await.expect("No adapter available for headless rendering");
+        let (device, queue) = Self::request_device(&adapter).await;
+
+        // サーフェスは無いが、大きさと形式の管理には同じ設定を使う
+        let surface_config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            width: width.max(1),
+            height: height.max(1),
+            present_mode: wgpu::PresentMode::Fifo,
+            desired_maximum_frame_latency: 2,
+            alpha_mode: wgpu::CompositeAlphaMode::Auto,
+            view_formats: vec![],
+        };
+        let texture = Self::create_offscreen_texture(&device, &surface_config);
+
+        Self::with_target(RenderTarget::Offscreen(texture), adapter, device, queue, surface_config)
+    }
+
+    fn create_offscreen_texture(device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) -> wgpu::Texture {
+        device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Offscreen Target"),
+            size: wgpu::Extent3d {
+                width: config.width,
+                height: config.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: config.format,
+            usage: config.usage,
+            view_formats: &[],
+        })
+    }
+
+    async fn request_device(adapter: &wgpu::Adapter) -> (wgpu::Device, wgpu::Queue) {
         let (device,queue) = adapter.request_device(
             &wgpu::DeviceDescriptor {
                 label: None,
-                required_features: wgpu::Features::empty(),
+                // 対応していればGPU時間の計測にタイムスタンプクエリを使い、
+                // MSAAのサンプル数はアダプタ固有の対応状況で選べるようにする
+                required_features: adapter.features()
+                    & (wgpu::Features::TIMESTAMP_QUERY | wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES),
                 ..Default::default()
             },
             None
@@ -53,12 +208,17 @@ impl<'window> WgpuStarCtx<'window> {
             eprintln!("Device: error: {:?}",err)
         }));
 
-        let size = window.inner_size();
-        let (width,height) = (size.width.max(1),size.width.max(1));
-        let surface_config = surface
-            .get_default_config(&adapter, width, height)
-            .unwrap();
-        surface.configure(&device, &surface_config);
+        (device, queue)
+    }
+
+    /// 描画先が決まった後の共通の初期化
+    fn with_target(
+        target: RenderTarget<'window>,
+        adapter: wgpu::Adapter,
+        device: wgpu::Device,
+        queue: wgpu::Queue,
+        surface_config: wgpu::SurfaceConfiguration,
+    ) -> Self {
 
         let bind_group_layout = device.create_bind_group_layout(
             &wgpu::BindGroupLayoutDescriptor {
@@ -78,10 +238,107 @@ impl<'window> WgpuStarCtx<'window> {
             }
         );
 
+        let ordering = StarOrdering::default();
+        let render_pipeline = Self::create_render_pipeline(
+            &device,
+            &bind_group_layout,
+            surface_config.format,
+            1,
+            Some(ordering.depth_stencil())
+        );
+        let depth_texture = depth::create_depth_texture(&device, (surface_config.width, surface_config.height), 1);
+
+        let vertices: Vec<PositionVertex> = PositionVertex::new_strip(PositionVertex::STAR_VERTEX_SIZE * 2);
+        let vertex_buffer = PositionVertex::get_buffer(&device, PositionVertex::vertices_byte(&vertices));
+
+        // 星の配置は毎フレーム作り直さず、初期化時に一度だけ決める
+        let mut instances: Vec<StarInstance> = StarInstance::new_vec(Self::NUM_STARS);
+        ordering.sort(&mut instances);
+        // インスタンスデータのバッファ化
+        let instance_buffer = StarInstance::get_buffer(&device, &instances);
+
+        let (uniform_buffer, uniform_bind_group) = TimeUniform::get_time_uniform_buffer_and_bindgroup(
+            &device,
+            &render_pipeline
+        );
+
+        let overlay = StatsOverlay::new(&device, surface_config.format, 1, Some(depth::ignore_depth()));
+        let sdf = SdfStarPipeline::new(&device, &uniform_buffer, surface_config.format, 1, Some(ordering.depth_stencil()));
+        let warp = WarpPipeline::new(&device, &uniform_buffer, surface_config.format, 1, Some(depth::test_depth()));
+        let lit = LitStarPipeline::new(
+            &device,
+            &uniform_buffer,
+            warp.camera_buffer(),
+            surface_config.format,
+            1,
+            Some(depth::test_depth())
+        );
+        let background = BackgroundPipeline::new(&device, &queue, surface_config.format, 1, Some(depth::ignore_depth()));
+        let gpu_timer = GpuTimer::new(&device, &queue);
+
+        Self {
+            target,
+            device,
+            queue,
+            surface_config,
+            render_pipeline,
+            uniform_bind_group_layout: bind_group_layout,
+            render_mode: StarRenderMode::default(),
+            sdf,
+            sprites: None,
+            warp,
+            lit,
+            sample_count: 1,
+            msaa_texture: None,
+            ordering,
+            depth_texture,
+            background,
+            bloom: None,
+            trails: None,
+            adapter,
+            vertex_buffer,
+            vertex_count: vertices.len() as u32,
+            instance_buffer,
+            instance_count: instances.len() as u32,
+            instances,
+            simulation: SimulationMode::default(),
+            last_step: 0.0,
+            edges: EdgeMode::default(),
+            collisions: false,
+            forces: Vec::new(),
+            boids: None,
+            emitters: None,
+            fireworks: None,
+            curves: None,
+            grid: SpatialGrid::default(),
+            pointer: None,
+            touch_points: Vec::new(),
+            view: View::default(),
+            interaction: InteractionSettings::default(),
+            uniform_buffer,
+            uniform_bind_group,
+            time_uniform: TimeUniform::new(),
+            overlay,
+            stats: FrameStats::new(),
+            gpu_timer,
+            gpu_done: Arc::new(Mutex::new(None)),
+            clock: Clock::new(),
+            screenshot: None,
+        }
+    }
+
+    /// 星を描画するパイプラインを作る
+    fn create_render_pipeline(
+        device: &wgpu::Device,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+        depth_stencil: Option<wgpu::DepthStencilState>,
+    ) -> wgpu::RenderPipeline {
         let pipeline_layout = device.create_pipeline_layout(
             &wgpu::PipelineLayoutDescriptor {
                 label: Some("Star Pipeline Layout"),
-                bind_group_layouts: &[&bind_group_layout],
+                bind_group_layouts: &[bind_group_layout],
                 push_constant_ranges: &[]
             }
         );
@@ -91,7 +348,7 @@ impl<'window> WgpuStarCtx<'window> {
             source: ShaderSource::Wgsl(Cow::Borrowed(include_str!("../star_shader.wgsl"))),
         });
 
-        let render_pipeline = device.create_render_pipeline(
+        device.create_render_pipeline(
             &wgpu::RenderPipelineDescriptor {
                 label: Some("Star Pipeline"),
                 layout: Some(&pipeline_layout),
@@ -112,8 +369,11 @@ impl<'window> WgpuStarCtx<'window> {
                     polygon_mode: wgpu::PolygonMode::Fill,
                     conservative: false
                 },
-                depth_stencil: None,
-                multisample: wgpu::MultisampleState::default(),
+                depth_stencil,
+                multisample: wgpu::MultisampleState {
+                    count: sample_count,
+                    ..Default::default()
+                },
                 multiview: None,
                 cache: None,
                 fragment: Some(wgpu::FragmentState {
@@ -121,21 +381,235 @@ impl<'window> WgpuStarCtx<'window> {
                     entry_point: Some("fragmentMain"),
                     targets: &[Some(wgpu::ColorTargetState {
                         write_mask: wgpu::ColorWrites::ALL,
-                        format: surface_config.format,
-                        blend: Some(wgpu::BlendState::REPLACE),
+                        format,
+                        blend: Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
                     })],
                     compilation_options: Default::default(),
                 })
             }
+        )
+    }
+
+    /// 指定したサンプル数以下でデバイスが対応している最大のサンプル数
+    /// wgpu の検証と同じく、アダプタ固有の形式の機能を有効にできなかった時は
+    /// WebGPUで必ず使える範囲（1と4）に限る（WebGPUに準拠しないアダプタを除く）
+    fn supported_sample_count(
+        adapter: &wgpu::Adapter,
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        requested: u32,
+    ) -> u32 {
+        let adapter_specific = device.features().contains(wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES)
+            || !adapter.get_downlevel_capabilities().is_webgpu_compliant();
+        let flags = |format: wgpu::TextureFormat| match adapter_specific {
+            true => adapter.get_texture_format_features(format).flags,
+            false => format.guaranteed_format_features(device.features()).flags,
+        };
+        // 深度バッファも同じサンプル数で作る
+        let (color, depth) = (flags(format), flags(depth::DEPTH_FORMAT));
+        [8, 4, 2]
+            .into_iter()
+            .find(|count| {
+                *count <= requested && color.sample_count_supported(*count) && depth.sample_count_supported(*count)
+            })
+            .unwrap_or(1)
+    }
+
+    fn create_msaa_texture(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+    ) -> Option<wgpu::Texture> {
+        if sample_count <= 1 {
+            return None;
+        }
+        Some(device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("MSAA Target"),
+            size: wgpu::Extent3d {
+                width: config.width,
+                height: config.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        }))
+    }
+
+    /// MSAAのサンプル数（1/2/4/8）を設定し、実際に使うサンプル数を返す
+    /// デバイスが対応していない場合は対応している範囲まで下げる
+    pub fn set_sample_count(&mut self, requested: u32) -> u32 {
+        let sample_count = Self::supported_sample_count(&self.adapter, &self.device, self.scene_format(), requested);
+        if sample_count != requested {
+            eprintln!("MSAA x{} is not supported by the device, using x{}", requested, sample_count);
+        }
+        self.sample_count = sample_count;
+        self.rebuild_pipelines();
+        sample_count
+    }
+
+    /// ブルームを有効にする（`None` なら無効にする）
+    /// 星空の描画先が変わるので、MSAAのサンプル数も合わせ直す
+    pub fn set_bloom(&mut self, settings: Option<BloomSettings>) {
+        self.bloom = settings.map(|settings| Bloom::new(
+            &self.device,
+            self.surface_config.format,
+            (self.surface_config.width, self.surface_config.height),
+            settings
+        ));
+        self.set_sample_count(self.sample_count);
+    }
+
+    /// 星の軌跡を有効にする（`None` なら無効にする）
+    pub fn set_trails(&mut self, settings: Option<TrailSettings>) {
+        self.trails = settings.map(|settings| Trails::new(
+            &self.device,
+            self.scene_format(),
+            (self.surface_config.width, self.surface_config.height),
+            settings
+        ));
+        self.set_sample_count(self.sample_count);
+    }
+
+    /// スプライトで描く時のアトラスを設定する
+    pub fn set_sprite_atlas(&mut self, atlas: &SpriteAtlas) {
+        self.sprites = Some(SpritePipeline::new(
+            &self.device,
+            &self.queue,
+            &self.uniform_buffer,
+            atlas,
+            self.scene_format(),
+            self.sample_count,
+            Some(self.ordering.depth_stencil())
+        ));
+    }
+
+    /// 重なった星を描く順を切り替える
+    pub fn set_ordering(&mut self, ordering: StarOrdering) {
+        self.ordering = ordering;
+        if !self.has_slots() {
+            self.ordering.sort(&mut self.instances);
+        }
+        self.queue.write_buffer(&self.instance_buffer, 0, bytemuck::cast_slice(&self.instances));
+        self.rebuild_pipelines();
+    }
+
+    /// 背景を切り替える
+    pub fn set_background(&mut self, settings: BackgroundSettings) {
+        self.background.set_settings(&self.device, &self.queue, settings);
+    }
+
+    /// 星空を描くテクスチャの形式
+    fn scene_format(&self) -> wgpu::TextureFormat {
+        match self.bloom {
+            Some(_) => Bloom::FORMAT,
+            None => self.surface_config.format,
+        }
+    }
+
+    /// 描画先の形式とサンプル数に合わせてパイプラインとMSAA用テクスチャを作り直す
+    fn rebuild_pipelines(&mut self) {
+        let scene_format = self.scene_format();
+        self.render_pipeline = Self::create_render_pipeline(
+            &self.device,
+            &self.uniform_bind_group_layout,
+            scene_format,
+            self.sample_count,
+            Some(self.ordering.depth_stencil())
         );
+        self.sdf.set_target(&self.device, scene_format, self.sample_count, Some(self.ordering.depth_stencil()));
+        // ワープの星は時間で z が変わり、立体の星は面どうしも重なるので、並べ方に関わらず深度バッファを使う
+        self.warp.set_target(&self.device, scene_format, self.sample_count, Some(depth::test_depth()));
+        self.lit.set_target(&self.device, scene_format, self.sample_count, Some(depth::test_depth()));
+        if let Some(sprites) = self.sprites.as_mut() {
+            sprites.set_target(&self.device, scene_format, self.sample_count, Some(self.ordering.depth_stencil()));
+        }
+        // 星空の形式が変わることがあるので軌跡も作り直す
+        if let Some(trails) = self.trails.take() {
+            self.trails = Some(Trails::new(
+                &self.device,
+                scene_format,
+                (self.surface_config.width, self.surface_config.height),
+                trails.settings
+            ));
+        }
+        // 軌跡を使う時、背景は軌跡を重ねる前に別のパスで描く
+        // 星空と同じパスで描く時は、深度バッファがあっても深度は無視する
+        let (background_sample_count, background_depth) = match self.trails {
+            Some(_) => (1, None),
+            None => (self.sample_count, Some(depth::ignore_depth())),
+        };
+        self.background.set_target(&self.device, scene_format, background_sample_count, background_depth);
+        // ブルームや軌跡を使う時、オーバーレイは最後に描画先へ直接描く
+        let (overlay_sample_count, overlay_depth) = match self.overlay_after_post() {
+            true => (1, None),
+            false => (self.sample_count, Some(depth::ignore_depth())),
+        };
+        self.overlay.set_target(&self.device, overlay_sample_count, overlay_depth);
+        self.msaa_texture = Self::create_msaa_texture(&self.device, &self.surface_config, scene_format, self.sample_count);
+        self.depth_texture = depth::create_depth_texture(
+            &self.device,
+            (self.surface_config.width, self.surface_config.height),
+            self.sample_count
+        );
+    }
 
-        Self {
-            surface,
-            device,
-            queue,
-            surface_config,
-            render_pipeline,
-            adapter
+    /// オーバーレイを星空と同じパスではなく、後処理の後に描くか
+    fn overlay_after_post(&self) -> bool {
+        self.bloom.is_some() || self.trails.is_some()
+    }
+
+    /// プレゼントモードを切り替える（描画タイミングの設定に合わせる）
+    pub fn set_present_mode(&mut self, present_mode: wgpu::PresentMode) {
+        self.surface_config.present_mode = present_mode;
+        self.configure_target();
+    }
+
+    /// デスクトップと合成できるアルファモード（乗算済み）に切り替え、選んだモードを返す
+    /// どのパイプラインも乗算済みの色を書くので、PostMultiplied では縁や光彩が暗くなる
+    /// PreMultiplied が無い時は不透明のまま警告する
+    pub fn use_compositing_alpha(&mut self) -> wgpu::CompositeAlphaMode {
+        let RenderTarget::Surface(surface) = &self.target else {
+            return self.surface_config.alpha_mode;
+        };
+        let alpha_modes = surface.get_capabilities(&self.adapter).alpha_modes;
+        if alpha_modes.contains(&wgpu::CompositeAlphaMode::PreMultiplied) {
+            self.surface_config.alpha_mode = wgpu::CompositeAlphaMode::PreMultiplied;
+            self.configure_target();
+        } else {
+            eprintln!("The surface does not support premultiplied transparency (alpha modes: {:?})", alpha_modes);
+        }
+        self.surface_config.alpha_mode
+    }
+
+    /// 描画先を現在の設定に合わせる
+    fn configure_target(&mut self) {
+        match &mut self.target {
+            RenderTarget::Surface(surface) => surface.configure(&self.device, &self.surface_config),
+            RenderTarget::Offscreen(texture) => {
+                *texture = Self::create_offscreen_texture(&self.device, &self.surface_config);
+            }
+        }
+        self.msaa_texture = Self::create_msaa_texture(
+            &self.device,
+            &self.surface_config,
+            self.scene_format(),
+            self.sample_count
+        );
+        self.depth_texture = depth::create_depth_texture(
+            &self.device,
+            (self.surface_config.width, self.surface_config.height),
+            self.sample_count
+        );
+        if let Some(bloom) = self.bloom.as_mut() {
+            bloom.resize(&self.device, (self.surface_config.width, self.surface_config.height));
+        }
+        if let Some(trails) = self.trails.as_mut() {
+            trails.resize(&self.device, (self.surface_config.width, self.surface_config.height));
         }
     }
 
@@ -145,125 +619,454 @@ impl<'window> WgpuStarCtx<'window> {
         let (width, height) = new_size;
         self.surface_config.width = width.max(1);
         self.surface_config.height = height.max(1);
-        self.surface.configure(&self.device, &self.surface_config);
+        self.configure_target();
     }
 
+    /// 星の数を変えて配置を作り直す
+    pub fn set_star_count(&mut self, count: u32) {
+        self.set_instances(StarInstance::new_vec(count));
+    }
 
-    /// 描画用関数
-    pub fn draw(&mut self) {
-        
-        let mut vertices: Vec<PositionVertex> = PositionVertex::new_vecs(PositionVertex::STAR_VERTEX_SIZE * 2);
-        let clone_vertices = vertices.clone();
-        let vertices_bytes: &[u8]  = PositionVertex::vertices_byte(&clone_vertices);
-        let vertex_buffer = PositionVertex::get_buffer(&self.device, vertices_bytes);
-
-        let uniform = TimeUniform::new();
-        let uniform_buffer = self.device.create_buffer_init(
-            &wgpu::util::BufferInitDescriptor {
-                label: Some("Time Uniform Buffer"),
-                contents: bytemuck::cast_slice(&[uniform]),
-                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST
-            }
-        );
+    /// 星の配置を差し替え、時間を最初から数え直す
+    pub fn set_instances(&mut self, instances: Vec<StarInstance>) {
+        self.instances = instances;
+        self.upload_instances();
+        self.clock.reset();
+        self.last_step = 0.0;
+    }
 
-        let bind_group_layout = self.device.create_bind_group_layout(
-            &wgpu::BindGroupLayoutDescriptor {
-                label: Some("Time Uniform Bind Group Layout"),
-                entries: &[
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 0,
-                        visibility: wgpu::ShaderStages::VERTEX,
-                        ty: wgpu::BindingType::Buffer {
-                            ty: wgpu::BufferBindingType::Uniform,
-                            has_dynamic_offset: false,
-                            min_binding_size: None,
-                        },
-                        count: None,
-                    }
-                ],
-            }
-        );
+    /// エミッターで星を生み出すようにする
+    /// 星は全て空の枠に置き換わり、インスタンスバッファはエミッターの枠の数で固定される
+    pub fn set_emitters(&mut self, mut emitters: EmitterSystem) {
+        emitters.restart();
+        let instances = emitters.empty_instances();
+        self.emitters = Some(emitters);
+        self.set_instances(instances);
+    }
 
-        let uniform_bind_group = self.device.create_bind_group(
-            &wgpu::BindGroupDescriptor {
-                label: Some("Time Uniform Bind Group"),
-                layout: &bind_group_layout,
-                entries: &[
-                    wgpu::BindGroupEntry {
-                        binding: 0,
-                        resource: uniform_buffer.as_entire_binding(),
-                    }
-                ],
-            }
-        );
+    /// 花火を打ち上げるようにする
+    /// 星は全て空の枠に置き換わり、インスタンスバッファは花火の枠の数で固定される
+    pub fn set_fireworks(&mut self, mut fireworks: Fireworks) {
+        fireworks.restart();
+        let instances = fireworks.empty_instances();
+        self.fireworks = Some(fireworks);
+        self.set_instances(instances);
+    }
 
-        let surface_texture = self.surface
-            .get_current_texture()
-            .expect("Failed to acquire next surface texture");
+    /// エミッターか花火の決まった数の枠に星を入れているか
+    /// 枠の星は生まれた順に入れ替わるので、並べ替えたり数を変えたりしない
+    fn has_slots(&self) -> bool {
+        self.emitters.is_some() || self.fireworks.is_some()
+    }
 
-        let texture_view = surface_texture
-            .texture
-            .create_view(
-                &wgpu::TextureViewDescriptor::default()
-            );
+    /// エミッターか花火の枠に `f` で星を入れる
+    fn with_slots(&mut self, f: impl FnOnce(&mut EmitterSystem, &mut [StarInstance])) {
+        let slots = match (self.emitters.as_mut(), self.fireworks.as_mut()) {
+            (Some(emitters), _) => emitters,
+            (None, Some(fireworks)) => fireworks.slots_mut(),
+            (None, None) => return,
+        };
+        f(slots, &mut self.instances);
+    }
 
-        let current_time = uniform.after_duration();
+    /// エミッターか花火の枠と同じ順の、星の年齢と寿命
+    /// `instances` を書き換えながら読めるように、枠を持つフィールドだけを借りる
+    fn slot_lives<'a>(emitters: &'a Option<EmitterSystem>, fireworks: &'a Option<Fireworks>) -> Option<&'a [StarLife]> {
+        match (emitters, fireworks) {
+            (Some(emitters), _) => Some(emitters.lives()),
+            (None, Some(fireworks)) => Some(fireworks.slots().lives()),
+            (None, None) => None,
+        }
+    }
 
-        self.queue.write_buffer(
-            &uniform_buffer,
-            0,
-            bytemuck::cast_slice(&[current_time])
-        );
+    /// `instances` を並べ替えてインスタンスバッファを作り直す
+    fn upload_instances(&mut self) {
+        if !self.has_slots() {
+            self.ordering.sort(&mut self.instances);
+        }
+        self.instance_buffer = StarInstance::get_buffer(&self.device, &self.instances);
+        self.instance_count = self.instances.len() as u32;
+    }
+
+    /// 今の時刻の位置にいる星を追加する
+    /// エミッターか花火を使っている時は、インスタンスバッファを作り直さずに空いた枠に入れる
+    pub fn add_instances(&mut self, mut instances: Vec<StarInstance>) {
+        if self.simulation == SimulationMode::Gpu {
+            // シェーダーは最初からの経過時間で動かすので、その分を戻しておく
+            simulation::step_cpu(&mut instances, -self.last_step, EdgeMode::Wrap);
+        }
+        if self.has_slots() {
+            self.with_slots(|slots, slot_instances| slots.insert(slot_instances, instances));
+            return;
+        }
+        self.instances.extend(instances);
+        self.upload_instances();
+    }
+
+    /// `at` から星が飛び散るように追加する
+    pub fn spawn_burst(&mut self, at: [f32; 2]) {
+        if self.has_slots() {
+            // 枠に入れる星は寿命が尽きると枠を空ける
+            let (emitter, count) = (interaction::burst_emitter(at), self.interaction.burst_count);
+            self.with_slots(|slots, instances| slots.emit(instances, &emitter, count));
+            return;
+        }
+        let stars = interaction::spawn_burst(at, self.interaction.burst_count, &mut rand::thread_rng());
+        self.add_instances(stars);
+    }
+
+    /// 今の時刻での星の位置
+    fn current_positions(&self) -> Vec<[f32; 2]> {
+        let mut current = self.instances.clone();
+        if self.simulation == SimulationMode::Gpu {
+            simulation::step_cpu(&mut current, self.last_step, EdgeMode::Wrap);
+        }
+        current.iter().map(|star| star.position).collect()
+    }
+
+    /// `at` の近くにいる星を消し、消した数を返す
+    pub fn remove_stars_near(&mut self, at: [f32; 2]) -> usize {
+        let positions = self.current_positions();
+        if self.has_slots() {
+            // 枠の星は取り除かずに寿命を終わらせ、次の更新で枠を空ける
+            let (radius, mut expired) = (self.interaction.remove_radius, 0);
+            self.with_slots(|slots, _| expired = interaction::expire_near(slots.lives_mut(), &positions, at, radius));
+            return expired;
+        }
+        let removed = interaction::remove_near(&mut self.instances, &positions, at, self.interaction.remove_radius);
+        if removed > 0 {
+            self.upload_instances();
+        }
+        removed
+    }
+
+    /// `from` の近くにいる星を `to` へ動かし、動かした数を返す
+    pub fn drag_stars(&mut self, from: [f32; 2], to: [f32; 2]) -> usize {
+        let positions = self.current_positions();
+        let moved = interaction::drag(&mut self.instances, &positions, from, to, self.interaction.drag_radius);
+        if moved > 0 {
+            self.queue.write_buffer(&self.instance_buffer, 0, bytemuck::cast_slice(&self.instances));
+        }
+        moved
+    }
+
+    /// オフスクリーンの描画結果をRGBA8の画素列として読み出す
+    /// ヘッドレスのコンテキストでのみ使える
+    pub fn read_pixels(&self) -> Option<Vec<u8>> {
+        let RenderTarget::Offscreen(texture) = &self.target else {
+            return None;
+        };
+        Some(self.read_texture(texture))
+    }
+
+    /// 描画先と同じ大きさのテクスチャを1画素4バイトの画素列として読み出す
+    fn read_texture(&self, texture: &wgpu::Texture) -> Vec<u8> {
+        let (width, height) = (self.surface_config.width, self.surface_config.height);
+        let unpadded_row = width * 4;
+        let padded_row = unpadded_row.div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT)
+            * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
 
+        let readback_buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Offscreen Readback Buffer"),
+            size: padded_row as u64 * height as u64,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
         let mut command_encoder = self.device.create_command_encoder(
             &wgpu::CommandEncoderDescriptor {
-                label: Some("Command Encoder")
+                label: Some("Readback Encoder")
             }
         );
+        command_encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &readback_buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_row),
+                    rows_per_image: Some(height),
+                },
+            },
+            texture.size(),
+        );
+        self.queue.submit(Some(command_encoder.finish()));
 
+        let slice = readback_buffer.slice(..);
+        slice.map_async(wgpu::MapMode::Read, |result| result.expect("Failed to map readback buffer"));
+        self.device.poll(wgpu::Maintain::Wait);
 
-        /* let mut indices: Vec<u16> = Vec::new();
-        for i in 0..PositionVertex::STAR_VERTEX_SIZE * 2 {
-            indices.push(i as u16);
-            indices.push((i + 5) as u16);
-            indices.push((i + 1) as u16 % (5 as u16 * 2));
-            indices.push(5 as u16 * 2 as u16);
+        let data = slice.get_mapped_range();
+        let pixels = data
+            .chunks_exact(padded_row as usize)
+            .flat_map(|row| &row[..unpadded_row as usize])
+            .copied()
+            .collect();
+        drop(data);
+        readback_buffer.unmap();
+        pixels
+    }
+
+    /// 次に描いたフレームをPNGに保存する
+    pub fn request_screenshot(&mut self, path: PathBuf) {
+        self.screenshot = Some(path);
+    }
+
+    fn save_screenshot(&self, texture: &wgpu::Texture, path: &Path) {
+        if !texture.usage().contains(wgpu::TextureUsages::COPY_SRC) {
+            eprintln!("Screenshots are not supported by the surface");
+            return;
         }
-        vertices.push(PositionVertex::CENTER);
-        let indices_bytes: &[u8] = bytemuck::cast_slice(
-            indices.as_slice()
-        );
+        let mut pixels = self.read_texture(texture);
+        if matches!(texture.format(), wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb) {
+            pixels.chunks_exact_mut(4).for_each(|pixel| pixel.swap(0, 2));
+        }
+        let image = RgbaImage { width: texture.width(), height: texture.height(), pixels };
+        match image.save_png(path) {
+            Ok(()) => println!("Saved screenshot to {}", path.display()),
+            Err(err) => eprintln!("Failed to save screenshot {}: {}", path.display(), err),
+        }
+    }
 
 
-    
-        
+    /// 描画用関数
+    pub fn draw(&mut self) {
+        let time = self.clock.tick();
+        self.draw_at(time);
+    }
 
-        /* インデックスデータをバッファとして用意 */
-        let index_buffer = self.device.create_buffer_init(
-            &wgpu::util::BufferInitDescriptor {
-                label: None,
-                usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST,
-                contents: indices_bytes
+    /// 経過時間を指定して描画する
+    pub fn draw_at(&mut self, time: f32) {
+        let surface_texture = match &self.target {
+            RenderTarget::Surface(surface) => Some(
+                surface
+                    .get_current_texture()
+                    .expect("Failed to acquire next surface texture")
+            ),
+            RenderTarget::Offscreen(_) => None,
+        };
+        // サーフェスの取得で垂直同期を待つので、計測はその後から始める
+        let frame_start = self.stats.begin_frame();
+        if let Some(gpu_timer) = self.gpu_timer.as_mut() {
+            let passes = gpu_timer.collect(&self.device);
+            if !passes.is_empty() {
+                self.stats.record_gpu_time(passes.iter().map(|(_, time)| *time).sum());
             }
-        );
+            for (label, time) in passes {
+                self.stats.record_gpu_pass(label, time);
+            }
+        } else if let Some(gpu_time) = self.gpu_done.lock().unwrap().take() {
+            self.stats.record_gpu_time(gpu_time);
+        }
 
-        let (uniform_buffer,uniform_bind_group) = TimeUniform::get_time_uniform_buffer_and_bindgroup(
-            &self.device,
-            &self.render_pipeline
+        let mut current_time = self.time_uniform.at(time);
+        current_time.view_offset = self.view.offset;
+        current_time.view_zoom = self.view.zoom;
+        if self.simulation == SimulationMode::Cpu {
+            let step = (current_time.time - self.last_step).min(simulation::MAX_STEP);
+            // カーソルと指の力はCPUで動かす時だけ働く
+            for pointer in self.pointer.iter().chain(&self.touch_points) {
+                interaction::apply_pointer_force(&mut self.instances, *pointer, &self.interaction, step);
+            }
+            forces::apply_forces(&mut self.instances, &self.forces, current_time.time, step);
+            if let Some(settings) = &self.boids {
+                let lives = Self::slot_lives(&self.emitters, &self.fireworks);
+                boids::apply_boids(&mut self.instances, lives, settings, &mut self.grid, step);
+            }
+            // CPUで動かした位置をそのまま使うので、シェーダー側では時間で動かさない
+            simulation::step_cpu(&mut self.instances, step, self.edges);
+            self.with_slots(|slots, instances| slots.advance(instances, step));
+            // 曲線は寿命のある枠の星にだけ掛かる
+            if let (Some(curves), Some(lives)) = (&self.curves, Self::slot_lives(&self.emitters, &self.fireworks)) {
+                curves.advance_rotation(&mut self.instances, lives, step);
+            }
+            if self.collisions {
+                collision::resolve_collisions(&mut self.instances, &mut self.grid);
+            }
+            if self.boids.is_some() {
+                boids::face_heading(&mut self.instances);
+            }
+            if let Some(emitters) = self.emitters.as_mut() {
+                emitters.update(&mut self.instances, current_time.time, step);
+            }
+            if let Some(fireworks) = self.fireworks.as_mut() {
+                fireworks.update(&mut self.instances, current_time.time, step);
+            }
+            if self.curves.is_some() || self.has_slots() {
+                // 曲線は描くためのコピーに掛け、星が元から持つ大きさなどは残す
+                // 枠の星は生まれた順に並んでいるので、描く順にはコピーを並べ替える
+                let mut shown = self.instances.clone();
+                if let (Some(curves), Some(lives)) = (&self.curves, Self::slot_lives(&self.emitters, &self.fireworks)) {
+                    curves.apply_appearance(&mut shown, lives);
+                }
+                self.ordering.sort(&mut shown);
+                self.queue.write_buffer(&self.instance_buffer, 0, bytemuck::cast_slice(&shown));
+            } else {
+                self.queue.write_buffer(&self.instance_buffer, 0, bytemuck::cast_slice(&self.instances));
+            }
+            current_time.motion_time = 0.0;
+        }
+        let dt = (current_time.time - self.last_step).max(0.0);
+        self.last_step = current_time.time;
+        self.queue.write_buffer(
+            &self.uniform_buffer,
+            0,
+            bytemuck::cast_slice(&[current_time])
         );
+        match self.render_mode {
+            StarRenderMode::Sdf => self.sdf.write_style(&self.queue),
+            StarRenderMode::Warp => self.warp.write_camera(
+                &self.queue,
+                (self.surface_config.width, self.surface_config.height)
+            ),
+            StarRenderMode::Lit => {
+                self.warp.write_camera(&self.queue, (self.surface_config.width, self.surface_config.height));
+                self.lit.write_light(&self.queue);
+            }
+            _ => {}
+        }
+        if let Some(bloom) = self.bloom.as_ref() {
+            bloom.write_settings(&self.queue);
+        }
+        if let Some(trails) = self.trails.as_ref() {
+            trails.write_settings(&self.queue, dt);
+        }
+        if self.background.is_visible() {
+            self.background.write_uniform(
+                &self.queue,
+                current_time.time,
+                (self.surface_config.width, self.surface_config.height)
+            );
+        }
+
+        let texture_view = match (&surface_texture, &self.target) {
+            (Some(surface_texture), _) => surface_texture.texture.create_view(&wgpu::TextureViewDescriptor::default()),
+            (None, RenderTarget::Offscreen(texture)) => texture.create_view(&wgpu::TextureViewDescriptor::default()),
+            (None, RenderTarget::Surface(_)) => unreachable!(),
+        };
 
-        
+        if self.overlay.visible {
+            let lines = StatsOverlay::lines(&self.stats, self.instance_count, &self.adapter.get_info());
+            self.overlay.prepare(
+                &self.queue,
+                (self.surface_config.width, self.surface_config.height),
+                &lines
+            );
+        }
 
-        
-         */
-        
+        let mut command_encoder = self.device.create_command_encoder(
+            &wgpu::CommandEncoderDescriptor {
+                label: Some("Command Encoder")
+            }
+        );
 
-        
-        
+        // ブルームを使う時はHDRのテクスチャに描き、後で描画先へ合成する
+        let scene_view = self.bloom.as_ref().map(Bloom::scene_view);
+        let star_view = scene_view.as_ref().unwrap_or(&texture_view);
+        // 軌跡を使う時は今のフレームを別のテクスチャに描き、軌跡と重ねてから後段に渡す
+        let frame_view = self.trails.as_ref().map(Trails::frame_view);
+        let pass_view = frame_view.as_ref().unwrap_or(star_view);
+        // MSAAの時はマルチサンプルテクスチャに描いて描画先へ解決する
+        let msaa_view = self.msaa_texture
+            .as_ref()
+            .map(|texture| texture.create_view(&wgpu::TextureViewDescriptor::default()));
+        let (color_view, resolve_target, store) = match &msaa_view {
+            Some(msaa_view) => (msaa_view, Some(pass_view), wgpu::StoreOp::Discard),
+            None => (pass_view, None, wgpu::StoreOp::Store),
+        };
+        let depth_view = self.depth_texture.create_view(&wgpu::TextureViewDescriptor::default());
 
         {
             let mut rpass = command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: None,
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: color_view,
+                    resolve_target,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store,
+                    },
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &depth_view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: wgpu::StoreOp::Discard,
+                    }),
+                    stencil_ops: None,
+                }),
+                timestamp_writes: self.gpu_timer.as_mut().and_then(|timer| timer.pass_writes("STARS")),
+                occlusion_query_set: None,
+            });
+
+            if self.trails.is_none() {
+                self.background.draw(&mut rpass);
+            }
+
+            match self.render_mode {
+                StarRenderMode::Mesh => {
+                    rpass.set_pipeline(
+                        &self.render_pipeline
+                    );
+
+                    rpass.set_bind_group(
+                        0,
+                        &self.uniform_bind_group,
+                        &[]
+                    );
+                    rpass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+                    rpass.set_vertex_buffer(1, self.instance_buffer.slice(..));
+                    rpass.draw(0..self.vertex_count,0..self.instance_count);
+                }
+                StarRenderMode::Sdf => {
+                    self.sdf.draw(&mut rpass, &self.instance_buffer, self.instance_count);
+                }
+                StarRenderMode::Sprite => {
+                    if let Some(sprites) = self.sprites.as_ref() {
+                        sprites.draw(&mut rpass, &self.instance_buffer, self.instance_count);
+                    }
+                }
+                StarRenderMode::Warp => {
+                    self.warp.draw(
+                        &mut rpass,
+                        &self.vertex_buffer,
+                        self.vertex_count,
+                        &self.instance_buffer,
+                        self.instance_count
+                    );
+                }
+                StarRenderMode::Lit => {
+                    self.lit.draw(&mut rpass, &self.instance_buffer, self.instance_count);
+                }
+            }
+
+            if !self.overlay_after_post() {
+                self.overlay.draw(&mut rpass);
+            }
+        }
+        if let Some(trails) = self.trails.as_mut() {
+            {
+                let mut rpass = command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("Background"),
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view: star_view,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                            store: wgpu::StoreOp::Store,
+                        },
+                    })],
+                    depth_stencil_attachment: None,
+                    timestamp_writes: None,
+                    occlusion_query_set: None,
+                });
+                self.background.draw(&mut rpass);
+            }
+            trails.draw(&mut command_encoder, star_view, self.gpu_timer.as_mut());
+        }
+        if let Some(bloom) = self.bloom.as_ref() {
+            bloom.blur(&mut command_encoder, self.gpu_timer.as_mut());
+            let mut rpass = command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Bloom Composite"),
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
                     view: &texture_view,
                     resolve_target: None,
@@ -273,31 +1076,52 @@ impl<'window> WgpuStarCtx<'window> {
                     },
                 })],
                 depth_stencil_attachment: None,
+                timestamp_writes: self.gpu_timer.as_mut().and_then(|timer| timer.pass_writes("COMPOSITE")),
+                occlusion_query_set: None,
+            });
+            bloom.composite(&mut rpass);
+            self.overlay.draw(&mut rpass);
+        } else if self.trails.is_some() && self.overlay.visible {
+            let mut rpass = command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Overlay"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &texture_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
                 timestamp_writes: None,
                 occlusion_query_set: None,
             });
-
-            rpass.set_pipeline(
-                &self.render_pipeline
-            );
-
-            rpass.set_bind_group(
-                0,
-                &uniform_bind_group,
-                &[]
-            );
-            rpass.set_vertex_buffer(0, vertex_buffer.slice(..));
-
-            let instances: Vec<StarInstance> = StarInstance::new_vec(Self::NUM_STARS);
-            // インスタンスデータのバッファ化
-            let instance_buffer = StarInstance::get_buffer(&self.device, &instances);
-            
-            rpass.set_vertex_buffer(1, instance_buffer.slice(..));
-            rpass.draw(0..vertices.len() as u32,0..instances.len() as u32)
-
+            self.overlay.draw(&mut rpass);
+        }
+        if let Some(gpu_timer) = self.gpu_timer.as_ref() {
+            gpu_timer.resolve(&mut command_encoder);
         }
         self.queue.submit(Some(command_encoder.finish()));
-        surface_texture.present();
+        if let Some(gpu_timer) = self.gpu_timer.as_mut() {
+            gpu_timer.after_submit();
+        } else {
+            let submitted = Instant::now();
+            let gpu_done = Arc::clone(&self.gpu_done);
+            self.queue.on_submitted_work_done(move || {
+                *gpu_done.lock().unwrap() = Some(submitted.elapsed());
+            });
+        }
+        self.stats.end_frame(frame_start);
+        if let Some(path) = self.screenshot.take() {
+            match (&surface_texture, &self.target) {
+                (Some(surface_texture), _) => self.save_screenshot(&surface_texture.texture, &path),
+                (None, RenderTarget::Offscreen(texture)) => self.save_screenshot(texture, &path),
+                (None, RenderTarget::Surface(_)) => unreachable!(),
+            }
+        }
+        if let Some(surface_texture) = surface_texture {
+            surface_texture.present();
+        }
     }
 
-}
\ No newline at end of file
+}
@@ -0,0 +1,334 @@
+use std::path::Path;
+
+use crate::{ctx::background::parse_color, emitter::StarLife, vertex::instance::StarInstance};
+
+/// キーフレームから次のキーフレームまでの補間の仕方
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Easing {
+    #[default]
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+    /// CSS の `cubic-bezier(x1, y1, x2, y2)` と同じ3次ベジェ曲線
+    CubicBezier([f32; 4]),
+}
+
+impl Easing {
+    /// `linear` `ease-in` `ease-out` `ease-in-out` `bezier(<x1>,<y1>,<x2>,<y2>)`
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "linear" => Some(Easing::Linear),
+            "ease-in" => Some(Easing::EaseIn),
+            "ease-out" => Some(Easing::EaseOut),
+            "ease-in-out" => Some(Easing::EaseInOut),
+            _ => {
+                let args = value.strip_prefix("bezier(")?.strip_suffix(')')?;
+                let args = args.split(',').map(|arg| arg.trim().parse::<f32>()).collect::<Result<Vec<_>, _>>().ok()?;
+                match args.as_slice() {
+                    // x が単調に増えないと t から一つに決まらないので、x は 0..1 に限る
+                    [x1, y1, x2, y2] if (0.0..=1.0).contains(x1) && (0.0..=1.0).contains(x2) => {
+                        Some(Easing::CubicBezier([*x1, *y1, *x2, *y2]))
+                    }
+                    _ => None,
+                }
+            }
+        }
+    }
+
+    /// 0.0..1.0 の進み具合を補間の重みに直す
+    pub fn apply(&self, t: f32) -> f32 {
+        // CSS の ease-in / ease-out / ease-in-out と同じ制御点
+        match *self {
+            Easing::Linear => t,
+            Easing::EaseIn => cubic_bezier([0.42, 0.0, 1.0, 1.0], t),
+            Easing::EaseOut => cubic_bezier([0.0, 0.0, 0.58, 1.0], t),
+            Easing::EaseInOut => cubic_bezier([0.42, 0.0, 0.58, 1.0], t),
+            Easing::CubicBezier(points) => cubic_bezier(points, t),
+        }
+    }
+}
+
+/// 端点が (0, 0) と (1, 1) の3次ベジェ曲線で、x が `x` になる点の y
+fn cubic_bezier([x1, y1, x2, y2]: [f32; 4], x: f32) -> f32 {
+    let bezier = |a: f32, b: f32, s: f32| 3.0 * a * s * (1.0 - s).powi(2) + 3.0 * b * s * s * (1.0 - s) + s.powi(3);
+    let slope = |a: f32, b: f32, s: f32| 3.0 * a * (1.0 - s).powi(2) + 6.0 * (b - a) * s * (1.0 - s) + 3.0 * (1.0 - b) * s * s;
+    let x = x.clamp(0.0, 1.0);
+    // ニュートン法で x(s) = x となる s を求め、収束しなければ二分法に切り替える
+    let mut s = x;
+    for _ in 0..8 {
+        let (error, d) = (bezier(x1, x2, s) - x, slope(x1, x2, s));
+        if error.abs() < 1e-5 {
+            return bezier(y1, y2, s);
+        }
+        if d.abs() < 1e-6 {
+            break;
+        }
+        s = (s - error / d).clamp(0.0, 1.0);
+    }
+    let (mut low, mut high) = (0.0, 1.0);
+    for _ in 0..20 {
+        s = (low + high) * 0.5;
+        if bezier(x1, x2, s) < x {
+            low = s;
+        } else {
+            high = s;
+        }
+    }
+    bezier(y1, y2, s)
+}
+
+/// キーフレームの間を補間できる値
+pub trait Lerp: Copy {
+    fn lerp(self, other: Self, t: f32) -> Self;
+}
+
+impl Lerp for f32 {
+    fn lerp(self, other: Self, t: f32) -> Self {
+        self + (other - self) * t
+    }
+}
+
+impl Lerp for [f32; 3] {
+    fn lerp(self, other: Self, t: f32) -> Self {
+        [0, 1, 2].map(|i| self[i].lerp(other[i], t))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Keyframe<T> {
+    /// 寿命を1とした時刻
+    pub time: f32,
+    pub value: T,
+    /// 次のキーフレームまでの補間の仕方
+    pub easing: Easing,
+}
+
+/// 寿命を1とした時刻で値が変わる曲線
+#[derive(Clone, Debug, PartialEq)]
+pub struct Curve<T> {
+    /// 時刻の順に並べたキーフレーム（1つ以上）
+    keyframes: Vec<Keyframe<T>>,
+}
+
+impl<T: Lerp> Curve<T> {
+    /// キーフレームを時刻の順に並べて曲線にする。空なら `None`
+    pub fn new(mut keyframes: Vec<Keyframe<T>>) -> Option<Self> {
+        keyframes.sort_by(|a, b| a.time.total_cmp(&b.time));
+        (!keyframes.is_empty()).then_some(Self { keyframes })
+    }
+
+    /// `<時刻>:<値>` と補間の仕方を空白で区切って並べたもの
+    /// 補間の仕方はその前のキーフレームから次のキーフレームまでに効く（省略すると `linear`）
+    ///
+    /// ```text
+    /// 0:0 ease-out 0.2:1 linear 1:0
+    /// ```
+    fn parse(value: &str, parse_value: impl Fn(&str) -> Option<T>) -> Option<Self> {
+        let mut keyframes: Vec<Keyframe<T>> = Vec::new();
+        for token in value.split_whitespace() {
+            match token.split_once(':') {
+                Some((time, value)) => keyframes.push(Keyframe {
+                    time: time.parse::<f32>().ok()?,
+                    value: parse_value(value)?,
+                    easing: Easing::Linear,
+                }),
+                None => keyframes.last_mut()?.easing = Easing::parse(token)?,
+            }
+        }
+        Self::new(keyframes)
+    }
+
+    /// 寿命を1とした時刻 `t` での値。最初と最後のキーフレームの外では端の値のまま
+    pub fn evaluate(&self, t: f32) -> T {
+        let next = self.keyframes.partition_point(|keyframe| keyframe.time <= t);
+        if next == 0 {
+            return self.keyframes[0].value;
+        }
+        let Some(to) = self.keyframes.get(next) else {
+            return self.keyframes[next - 1].value;
+        };
+        let from = &self.keyframes[next - 1];
+        let progress = (t - from.time) / (to.time - from.time);
+        from.value.lerp(to.value, from.easing.apply(progress))
+    }
+}
+
+/// 星の寿命に合わせて見た目と回転を変える曲線
+/// 大きさ・不透明度・回転の速さは星が元から持つ値に掛け、色は置き換える
+/// 寿命が無限の星には効かない
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LifetimeCurves {
+    pub scale: Option<Curve<f32>>,
+    pub opacity: Option<Curve<f32>>,
+    pub color: Option<Curve<[f32; 3]>>,
+    pub rotation_speed: Option<Curve<f32>>,
+}
+
+impl LifetimeCurves {
+    /// `<性質> = <キーフレーム>` を1行ずつ並べたシーンファイルを読む（`#` で始まる行はコメント）
+    /// 性質は `scale` `opacity` `color` `rotation-speed`。色の値は `#rrggbb`
+    /// 読めない行は警告して飛ばす
+    ///
+    /// ```text
+    /// scale = 0:0 ease-out 0.2:1 ease-in 1:0
+    /// color = 0:#ffffff 0.5:#ffff00 1:#ff4000
+    /// rotation-speed = 0:1 bezier(0.4,0,0.2,1) 1:4
+    /// ```
+    pub fn load(path: &Path) -> std::io::Result<Self> {
+        Ok(Self::parse(&std::fs::read_to_string(path)?))
+    }
+
+    pub fn parse(source: &str) -> Self {
+        let mut curves = Self::default();
+        let number = |value: &str| value.parse::<f32>().ok();
+        let color = |value: &str| parse_color(value).map(|[r, g, b, _]| [r, g, b]);
+        for (line_number, line) in source.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let parsed = match line.split_once('=').map(|(name, value)| (name.trim(), value)) {
+                Some(("scale", value)) => Curve::parse(value, number).map(|curve| curves.scale = Some(curve)),
+                Some(("opacity", value)) => Curve::parse(value, number).map(|curve| curves.opacity = Some(curve)),
+                Some(("color", value)) => Curve::parse(value, color).map(|curve| curves.color = Some(curve)),
+                Some(("rotation-speed", value)) => {
+                    Curve::parse(value, number).map(|curve| curves.rotation_speed = Some(curve))
+                }
+                _ => None,
+            };
+            if parsed.is_none() {
+                eprintln!("Invalid curve on line {}: {:?}", line_number + 1, line);
+            }
+        }
+        curves
+    }
+
+    /// 寿命を1とした星の年齢（寿命が無限か、もう尽きているなら `None`）
+    fn progress(life: &StarLife) -> Option<f32> {
+        (life.lifetime.is_finite() && life.is_alive()).then(|| (life.age / life.lifetime).clamp(0.0, 1.0))
+    }
+
+    /// 回転の速さの曲線に合わせて、dt 秒の間に足りない分（または余分な分）だけ回す
+    /// `step_cpu` が元の速さで回した後に呼ぶ。`lives` は `instances` と同じ順の年齢と寿命
+    pub fn advance_rotation(&self, instances: &mut [StarInstance], lives: &[StarLife], dt: f32) {
+        let Some(curve) = &self.rotation_speed else {
+            return;
+        };
+        for (star, life) in instances.iter_mut().zip(lives) {
+            if let Some(t) = Self::progress(life) {
+                star.initial_rotation += star.rotation_speed * (curve.evaluate(t) - 1.0) * dt;
+            }
+        }
+    }
+
+    /// 大きさ・不透明度・色を年齢に合わせて変える（描くためのコピーに使う）
+    pub fn apply_appearance(&self, instances: &mut [StarInstance], lives: &[StarLife]) {
+        for (star, life) in instances.iter_mut().zip(lives) {
+            let Some(t) = Self::progress(life) else {
+                continue;
+            };
+            if let Some(curve) = &self.scale {
+                star.scale *= curve.evaluate(t);
+            }
+            if let Some(curve) = &self.opacity {
+                star.opacity *= curve.evaluate(t);
+            }
+            if let Some(curve) = &self.color {
+                star.color = curve.evaluate(t);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(actual: f32, expected: f32) {
+        assert!((actual - expected).abs() < 1e-3, "{} != {}", actual, expected);
+    }
+
+    fn keyframe(time: f32, value: f32) -> Keyframe<f32> {
+        Keyframe { time, value, easing: Easing::Linear }
+    }
+
+    #[test]
+    fn css_easings_match_known_values() {
+        for easing in [Easing::EaseIn, Easing::EaseOut, Easing::EaseInOut] {
+            assert_near(easing.apply(0.0), 0.0);
+            assert_near(easing.apply(1.0), 1.0);
+        }
+        assert_near(Easing::EaseIn.apply(0.5), 0.31536);
+        assert_near(Easing::EaseOut.apply(0.5), 0.68464);
+        assert_near(Easing::EaseInOut.apply(0.25), 0.12916);
+        assert_near(Easing::EaseInOut.apply(0.5), 0.5);
+        // CSS の `ease`
+        let ease = Easing::CubicBezier([0.25, 0.1, 0.25, 1.0]);
+        assert_near(ease.apply(0.25), 0.40851);
+        assert_near(ease.apply(0.5), 0.8024);
+    }
+
+    #[test]
+    fn bezier_clamps_progress_and_allows_overshoot() {
+        let back = Easing::parse("bezier(0.3,-0.5,0.7,1.5)").unwrap();
+        assert_near(back.apply(-1.0), 0.0);
+        assert_near(back.apply(2.0), 1.0);
+        assert!(back.apply(0.1) < 0.0 && back.apply(0.9) > 1.0);
+        assert_eq!(Easing::parse("bezier(1.5,0,0.5,1)"), None);
+        assert_eq!(Easing::parse("bezier(0.5,0,1)"), None);
+    }
+
+    #[test]
+    fn evaluate_holds_the_end_values_outside_the_keyframes() {
+        let curve = Curve::new(vec![keyframe(0.8, 3.0), keyframe(0.2, 1.0)]).unwrap();
+        assert_eq!(curve.evaluate(0.0), 1.0);
+        assert_eq!(curve.evaluate(0.2), 1.0);
+        assert_near(curve.evaluate(0.5), 2.0);
+        assert_eq!(curve.evaluate(0.8), 3.0);
+        assert_eq!(curve.evaluate(1.0), 3.0);
+        assert_eq!(Curve::<f32>::new(Vec::new()), None);
+    }
+
+    #[test]
+    fn duplicate_times_jump_to_the_later_keyframe() {
+        let curve = Curve::new(vec![keyframe(0.0, 0.0), keyframe(0.5, 1.0), keyframe(0.5, 5.0), keyframe(1.0, 6.0)]).unwrap();
+        assert_near(curve.evaluate(0.25), 0.5);
+        assert_eq!(curve.evaluate(0.5), 5.0);
+        assert_near(curve.evaluate(0.75), 5.5);
+    }
+
+    #[test]
+    fn parses_lifetime_curves() {
+        let curves = LifetimeCurves::parse(
+            "# コメント\n\
+             \n\
+             scale = 0:0 ease-out 0.2:1 1:0\n\
+             color = 0:#ffffff 1:#ff0000\n\
+             rotation-speed = 0:1 bezier(0.4,0,0.2,1) 1:4\n\
+             opacity = 0:1 wobble 1:0\n\
+             size = 0:1\n",
+        );
+        let scale = curves.scale.unwrap();
+        assert_eq!(scale.keyframes[0].easing, Easing::EaseOut);
+        assert_eq!(scale.keyframes[1].easing, Easing::Linear);
+        assert_near(scale.evaluate(0.6), 0.5);
+        assert_eq!(curves.color.unwrap().evaluate(0.5), [1.0, 0.5, 0.5]);
+        assert_eq!(curves.rotation_speed.unwrap().keyframes[0].easing, Easing::CubicBezier([0.4, 0.0, 0.2, 1.0]));
+        // 読めない行は飛ばす
+        assert_eq!(curves.opacity, None);
+    }
+
+    #[test]
+    fn curves_only_apply_to_living_stars_with_a_lifetime() {
+        let curves = LifetimeCurves::parse("scale = 0:1 1:0\nopacity = 0:1 1:0\n");
+        let star = StarInstance { scale: 2.0, opacity: 1.0, ..bytemuck::Zeroable::zeroed() };
+        let life = StarLife { age: 1.0, lifetime: 4.0, bursts: 0 };
+        let mut instances = [star, star, star];
+        curves.apply_appearance(&mut instances, &[life, StarLife { lifetime: f32::INFINITY, ..life }, StarLife::EMPTY]);
+        assert_near(instances[0].scale, 1.5);
+        assert_near(instances[0].opacity, 0.75);
+        assert_eq!((instances[1].scale, instances[1].opacity), (2.0, 1.0));
+        assert_eq!((instances[2].scale, instances[2].opacity), (2.0, 1.0));
+    }
+}
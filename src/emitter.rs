@@ -0,0 +1,393 @@
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::vertex::instance::StarInstance;
+
+/// 最小値と最大値の間で一様に選ぶ値
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ValueRange {
+    pub min: f32,
+    pub max: f32,
+}
+
+impl ValueRange {
+    pub const fn new(min: f32, max: f32) -> Self {
+        Self { min, max }
+    }
+
+    /// `<最小>..<最大>` または `<値>`
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.split_once("..") {
+            Some((min, max)) => {
+                let (min, max) = (min.parse::<f32>().ok()?, max.parse::<f32>().ok()?);
+                (min <= max).then_some(Self::new(min, max))
+            }
+            None => value.parse::<f32>().ok().map(|value| Self::new(value, value)),
+        }
+    }
+
+    pub fn sample(&self, rng: &mut impl Rng) -> f32 {
+        if self.max > self.min {
+            rng.gen_range(self.min..self.max)
+        } else {
+            self.min
+        }
+    }
+}
+
+/// 星が生まれる場所の形
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EmitterShape {
+    Point([f32; 2]),
+    /// 2点を結ぶ線分の上
+    Line { from: [f32; 2], to: [f32; 2] },
+    /// 円周の上
+    Circle { center: [f32; 2], radius: f32 },
+    /// 長方形の内側
+    Rect { min: [f32; 2], max: [f32; 2] },
+}
+
+impl EmitterShape {
+    /// `point:<x>,<y>`
+    /// `line:<x1>,<y1>,<x2>,<y2>`
+    /// `circle:<x>,<y>,<半径>`
+    /// `rect:<x1>,<y1>,<x2>,<y2>`
+    pub fn parse(value: &str) -> Option<Self> {
+        let (kind, args) = value.split_once(':')?;
+        let args = args.split(',').map(str::parse::<f32>).collect::<Result<Vec<_>, _>>().ok()?;
+        match (kind, args.as_slice()) {
+            ("point", [x, y]) => Some(EmitterShape::Point([*x, *y])),
+            ("line", [x1, y1, x2, y2]) => Some(EmitterShape::Line { from: [*x1, *y1], to: [*x2, *y2] }),
+            ("circle", [x, y, radius]) if *radius >= 0.0 => {
+                Some(EmitterShape::Circle { center: [*x, *y], radius: *radius })
+            }
+            ("rect", [x1, y1, x2, y2]) => Some(EmitterShape::Rect {
+                min: [x1.min(*x2), y1.min(*y2)],
+                max: [x1.max(*x2), y1.max(*y2)],
+            }),
+            _ => None,
+        }
+    }
+
+    fn sample(&self, rng: &mut impl Rng) -> [f32; 2] {
+        match *self {
+            EmitterShape::Point(position) => position,
+            EmitterShape::Line { from, to } => {
+                let t = rng.gen_range(0.0..1.0);
+                [from[0] + (to[0] - from[0]) * t, from[1] + (to[1] - from[1]) * t]
+            }
+            EmitterShape::Circle { center, radius } => {
+                let angle = rng.gen_range(0.0..std::f32::consts::TAU);
+                [center[0] + angle.cos() * radius, center[1] + angle.sin() * radius]
+            }
+            EmitterShape::Rect { min, max } => [
+                ValueRange::new(min[0], max[0]).sample(rng),
+                ValueRange::new(min[1], max[1]).sample(rng),
+            ],
+        }
+    }
+}
+
+/// 星を生み出す場所と、生まれる星の性質
+#[derive(Clone, Debug, PartialEq)]
+pub struct Emitter {
+    pub shape: EmitterShape,
+    /// 1秒あたりに生まれる星の数
+    pub rate: f32,
+    /// 一度にまとめて生まれる星の数
+    pub burst_count: u32,
+    /// まとめて生まれる間隔（秒）。0なら最初の1回だけ
+    pub burst_interval: f32,
+    /// 寿命（秒）
+    pub lifetime: ValueRange,
+    /// 初速の大きさ（単位/秒）
+    pub speed: ValueRange,
+    /// 初速の向き（ラジアン、+x から反時計回り）
+    pub direction: ValueRange,
+    pub scale: ValueRange,
+    /// 最初の回転（ラジアン）
+    pub rotation: ValueRange,
+    /// 回転の速さ（ラジアン/秒）
+    pub rotation_speed: ValueRange,
+    /// 生まれる星の色（リニアなRGB）
+    pub color: [f32; 3],
+    /// 生まれた星が寿命の尽きた時に弾ける回数
+    pub bursts: u32,
+}
+
+impl Emitter {
+    pub fn new(shape: EmitterShape) -> Self {
+        use std::f32::consts::TAU;
+        Self {
+            shape,
+            rate: 50.0,
+            burst_count: 0,
+            burst_interval: 0.0,
+            lifetime: ValueRange::new(2.0, 4.0),
+            speed: ValueRange::new(0.1, 0.3),
+            direction: ValueRange::new(0.0, TAU),
+            scale: ValueRange::new(0.01, 0.05),
+            rotation: ValueRange::new(0.0, TAU),
+            rotation_speed: ValueRange::new(-1.0, 1.0),
+            color: StarInstance::DEFAULT_COLOR,
+            bursts: 0,
+        }
+    }
+
+    fn spawn(&self, rng: &mut impl Rng) -> (StarInstance, StarLife) {
+        let base = StarInstance::new_vec_with_rng(1, rng)[0];
+        let (speed, direction) = (self.speed.sample(rng), self.direction.sample(rng));
+        let scale = self.scale.sample(rng);
+        let star = StarInstance {
+            position: self.shape.sample(rng),
+            scale,
+            initial_rotation: self.rotation.sample(rng),
+            speed: [direction.cos() * speed, direction.sin() * speed],
+            rotation_speed: self.rotation_speed.sample(rng),
+            opacity: 1.0,
+            depth: StarInstance::depth_for_scale(scale),
+            color: self.color,
+            ..base
+        };
+        let life = StarLife { age: 0.0, lifetime: self.lifetime.sample(rng), bursts: self.bursts };
+        (star, life)
+    }
+}
+
+/// 枠に入った星の年齢と寿命（CPUでだけ使い、シェーダーには渡さない）
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct StarLife {
+    /// 生まれてからの秒数
+    pub age: f32,
+    /// 寿命（秒）。無限大なら消えない
+    pub lifetime: f32,
+    /// 寿命が尽きた時にあと何回弾けるか（花火で使う）
+    pub bursts: u32,
+}
+
+impl StarLife {
+    /// 空いた枠
+    pub const EMPTY: Self = Self { age: 0.0, lifetime: 0.0, bursts: 0 };
+    /// エミッターの外で作った、消えない星
+    pub const FOREVER: Self = Self { age: 0.0, lifetime: f32::INFINITY, bursts: 0 };
+
+    /// 寿命が尽きていないか
+    pub fn is_alive(&self) -> bool {
+        self.age < self.lifetime
+    }
+}
+
+/// エミッターごとの、次に星が生まれるまでの状態
+#[derive(Clone, Copy, Debug, Default)]
+struct SpawnTimer {
+    /// 1個に満たない分の生まれるはずだった星
+    pending: f32,
+    /// 次にまとめて生まれる時刻（`None` なら次の更新で生まれる）
+    next_burst: Option<f32>,
+}
+
+impl SpawnTimer {
+    /// `time` 秒までの dt 秒の間に `emitter` から生まれる星の数
+    fn spawn_count(&mut self, emitter: &Emitter, time: f32, dt: f32) -> u32 {
+        self.pending += emitter.rate * dt.max(0.0);
+        let mut count = self.pending.floor();
+        self.pending -= count;
+        match self.next_burst {
+            None => {
+                count += emitter.burst_count as f32;
+                let interval = emitter.burst_interval;
+                self.next_burst = Some(if interval > 0.0 { time + interval } else { f32::INFINITY });
+            }
+            Some(next) if time >= next => {
+                // 止まっていた間の分はまとめて生まず、1回だけ生んで次の時刻を今より後にする
+                let bursts = ((time - next) / emitter.burst_interval).floor() + 1.0;
+                count += emitter.burst_count as f32;
+                self.next_burst = Some(next + emitter.burst_interval * bursts);
+            }
+            Some(_) => {}
+        }
+        count as u32
+    }
+}
+
+/// 決まった数の枠を使い回して、エミッターから生まれた星を入れる
+/// 枠の数は変わらないので、インスタンスバッファを作り直さずに済む
+#[derive(Clone, Debug)]
+pub struct EmitterSystem {
+    pub emitters: Vec<Emitter>,
+    /// `emitters` と同じ順の状態
+    timers: Vec<SpawnTimer>,
+    /// 枠と同じ順の、星の年齢と寿命
+    lives: Vec<StarLife>,
+    capacity: u32,
+    /// 次に星を入れる枠
+    next: usize,
+    rng: StdRng,
+}
+
+impl EmitterSystem {
+    pub fn new(emitters: Vec<Emitter>, capacity: u32) -> Self {
+        Self::with_rng(emitters, capacity, StdRng::from_entropy())
+    }
+
+    /// シードを固定して同じ星の生まれ方を再現する
+    pub fn seeded(emitters: Vec<Emitter>, capacity: u32, seed: u64) -> Self {
+        Self::with_rng(emitters, capacity, StdRng::seed_from_u64(seed))
+    }
+
+    fn with_rng(emitters: Vec<Emitter>, capacity: u32, rng: StdRng) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            emitters,
+            timers: Vec::new(),
+            lives: vec![StarLife::EMPTY; capacity as usize],
+            capacity,
+            next: 0,
+            rng,
+        }
+    }
+
+    /// 時刻0から生み出し直せるように、まとめて生まれる時刻などを戻す
+    pub fn restart(&mut self) {
+        self.timers.clear();
+        self.lives.fill(StarLife::EMPTY);
+        self.next = 0;
+    }
+
+    /// 枠と同じ順の、星の年齢と寿命
+    pub fn lives(&self) -> &[StarLife] {
+        &self.lives
+    }
+
+    pub fn lives_mut(&mut self) -> &mut [StarLife] {
+        &mut self.lives
+    }
+
+    /// 全ての枠が空いた状態の星
+    pub fn empty_instances(&self) -> Vec<StarInstance> {
+        let empty = StarInstance {
+            rotation_axis: [0.0, 0.0, 1.0],
+            ..bytemuck::Zeroable::zeroed()
+        };
+        vec![empty; self.capacity as usize]
+    }
+
+    /// `next` から順に空いた枠を探す。空きが無ければ `next` の星を入れ替える
+    fn allocate(&mut self) -> usize {
+        let len = self.lives.len();
+        let slot = (0..len)
+            .map(|offset| (self.next + offset) % len)
+            .find(|&slot| !self.lives[slot].is_alive())
+            .unwrap_or(self.next % len);
+        self.next = (slot + 1) % len;
+        slot
+    }
+
+    /// 星の年齢を dt 秒進め、描画先の端から出た星の寿命を終わらせる
+    /// `step_cpu` で星を動かした後に呼ぶ
+    pub fn advance(&mut self, instances: &[StarInstance], dt: f32) {
+        for (star, life) in instances.iter().zip(self.lives.iter_mut()) {
+            life.age += dt;
+            // 端でラップしたり跳ね返ったりする星は出ないので、`EdgeMode::Vanish` の時だけ消える
+            if star.position.iter().any(|p| p.abs() > 1.0 + star.scale) {
+                life.lifetime = life.lifetime.min(life.age);
+            }
+        }
+    }
+
+    /// `time` 秒までの dt 秒の間に生まれる星を空いた枠に入れ、寿命が尽きた星を見えなくする
+    pub fn update(&mut self, instances: &mut [StarInstance], time: f32, dt: f32) {
+        if instances.is_empty() {
+            return;
+        }
+        for (star, life) in instances.iter_mut().zip(&self.lives) {
+            if !life.is_alive() {
+                star.scale = 0.0;
+                star.opacity = 0.0;
+            }
+        }
+        // エミッターが後から足されても、新しいものは次の更新で最初のまとまりを生む
+        self.timers.resize(self.emitters.len(), SpawnTimer::default());
+        for index in 0..self.emitters.len() {
+            let count = self.timers[index].spawn_count(&self.emitters[index], time, dt);
+            let emitter = self.emitters[index].clone();
+            self.emit(instances, &emitter, count);
+        }
+    }
+
+    /// `emitter` から `count` 個の星を今すぐ生み、空いた枠に入れる
+    pub fn emit(&mut self, instances: &mut [StarInstance], emitter: &Emitter, count: u32) {
+        if instances.is_empty() {
+            return;
+        }
+        // 枠より多く生まれても、入りきらない分は上書きされるだけなので数えない
+        for _ in 0..count.min(self.capacity) {
+            let slot = self.allocate();
+            (instances[slot], self.lives[slot]) = emitter.spawn(&mut self.rng);
+        }
+    }
+
+    /// エミッターの外で作った星を空いた枠に入れる（寿命は無く、消されるか上書きされるまで残る）
+    pub fn insert(&mut self, instances: &mut [StarInstance], stars: Vec<StarInstance>) {
+        if instances.is_empty() {
+            return;
+        }
+        let skip = stars.len().saturating_sub(self.capacity as usize);
+        for star in stars.into_iter().skip(skip) {
+            let slot = self.allocate();
+            (instances[slot], self.lives[slot]) = (star, StarLife::FOREVER);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bursting() -> Emitter {
+        Emitter { rate: 0.0, burst_count: 10, burst_interval: 1.0, ..Emitter::new(EmitterShape::Point([0.0, 0.0])) }
+    }
+
+    #[test]
+    fn bursts_on_the_first_update_and_every_interval() {
+        let (emitter, mut timer) = (bursting(), SpawnTimer::default());
+        assert_eq!(timer.spawn_count(&emitter, 0.0, 0.0), 10);
+        assert_eq!(timer.spawn_count(&emitter, 0.5, 0.5), 0);
+        assert_eq!(timer.spawn_count(&emitter, 1.0, 0.5), 10);
+        assert_eq!(timer.spawn_count(&emitter, 1.9, 0.9), 0);
+    }
+
+    #[test]
+    fn skips_bursts_missed_during_a_stall() {
+        let (emitter, mut timer) = (bursting(), SpawnTimer::default());
+        timer.spawn_count(&emitter, 0.0, 0.0);
+        // 5.5 秒止まっていても1回分だけ生み、次は 6 秒
+        assert_eq!(timer.spawn_count(&emitter, 5.5, 0.1), 10);
+        assert_eq!(timer.spawn_count(&emitter, 5.9, 0.1), 0);
+        assert_eq!(timer.spawn_count(&emitter, 6.0, 0.1), 10);
+    }
+
+    #[test]
+    fn stars_age_and_expire_when_they_leave_the_screen() {
+        let mut system = EmitterSystem::seeded(vec![], 2, 1);
+        let mut instances = system.empty_instances();
+        system.emit(&mut instances, &Emitter { lifetime: ValueRange::new(5.0, 5.0), ..bursting() }, 2);
+        instances[1].position = [1.5, 0.0];
+        system.advance(&instances, 0.5);
+        assert_eq!(system.lives()[0], StarLife { age: 0.5, lifetime: 5.0, bursts: 0 });
+        assert!(!system.lives()[1].is_alive());
+
+        // 寿命の尽きた枠は見えなくなり、次に生まれる星が入る
+        system.update(&mut instances, 0.5, 0.0);
+        assert_eq!((instances[1].scale, instances[1].opacity), (0.0, 0.0));
+        system.emit(&mut instances, &bursting(), 1);
+        assert!(system.lives()[1].is_alive() && system.lives()[0].age == 0.5);
+    }
+
+    #[test]
+    fn keeps_the_fraction_of_the_rate() {
+        let emitter = Emitter { rate: 15.0, ..Emitter::new(EmitterShape::Point([0.0, 0.0])) };
+        let mut timer = SpawnTimer::default();
+        let counts: Vec<u32> = (1..=4).map(|frame| timer.spawn_count(&emitter, frame as f32 * 0.1, 0.1)).collect();
+        assert_eq!(counts.iter().sum::<u32>(), 6, "{:?}", counts);
+    }
+}
@@ -0,0 +1,240 @@
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::{
+    emitter::{Emitter, EmitterShape, EmitterSystem, ValueRange},
+    forces::{self, ForceField},
+    vertex::instance::StarInstance,
+};
+
+/// 花火の設定
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FireworksSettings {
+    /// 1秒あたりに打ち上げる数
+    pub launch_rate: f32,
+    /// 1発の花火から飛び散る星の数
+    pub spark_count: u32,
+    /// 飛び散った星のうち、もう一度弾ける星の数（0なら二度目は弾けない）
+    pub secondary_count: u32,
+    /// 下向きの重力加速度（単位/秒²）
+    pub gravity: f32,
+    /// 空気の抵抗（1秒あたりに速度から引く割合）
+    pub drag: f32,
+}
+
+impl Default for FireworksSettings {
+    fn default() -> Self {
+        Self {
+            launch_rate: 1.5,
+            spark_count: 80,
+            secondary_count: 4,
+            gravity: 0.5,
+            drag: 1.2,
+        }
+    }
+}
+
+/// 弾けた星の色（リニアなRGB）
+const PALETTE: [[f32; 3]; 6] = [
+    [1.0, 0.2, 0.1],
+    [1.0, 0.8, 0.1],
+    [0.2, 1.0, 0.3],
+    [0.2, 0.5, 1.0],
+    [0.9, 0.3, 1.0],
+    [1.0, 1.0, 1.0],
+];
+
+/// 打ち上げた星が寿命の尽きた所で弾け、飛び散った星が重力と空気の抵抗を受けながら消えていく
+/// 星はエミッターと同じく決まった数の枠を使い回す
+#[derive(Clone, Debug)]
+pub struct Fireworks {
+    pub settings: FireworksSettings,
+    slots: EmitterSystem,
+    /// 弾ける色を選ぶ乱数
+    rng: StdRng,
+}
+
+impl Fireworks {
+    /// 二度目に弾ける時の星の数の割合
+    const SECONDARY_SCALE: f32 = 0.25;
+
+    pub fn new(settings: FireworksSettings, capacity: u32) -> Self {
+        Self::with_slots(settings, EmitterSystem::new(Vec::new(), capacity), StdRng::from_entropy())
+    }
+
+    /// シードを固定して同じ花火を再現する
+    pub fn seeded(settings: FireworksSettings, capacity: u32, seed: u64) -> Self {
+        Self::with_slots(
+            settings,
+            EmitterSystem::seeded(Vec::new(), capacity, seed),
+            StdRng::seed_from_u64(seed.wrapping_add(1)),
+        )
+    }
+
+    fn with_slots(settings: FireworksSettings, mut slots: EmitterSystem, rng: StdRng) -> Self {
+        slots.emitters = vec![Self::launcher(&settings)];
+        Self { settings, slots, rng }
+    }
+
+    /// 画面の下の端から打ち上げるエミッター
+    fn launcher(settings: &FireworksSettings) -> Emitter {
+        Emitter {
+            rate: settings.launch_rate,
+            lifetime: ValueRange::new(0.9, 1.3),
+            speed: ValueRange::new(1.3, 1.7),
+            direction: ValueRange::new(80f32.to_radians(), 100f32.to_radians()),
+            scale: ValueRange::new(0.015, 0.02),
+            rotation_speed: ValueRange::new(-6.0, 6.0),
+            color: [1.0, 0.9, 0.6],
+            bursts: if settings.secondary_count > 0 { 2 } else { 1 },
+            ..Emitter::new(EmitterShape::Line { from: [-0.7, -1.0], to: [0.7, -1.0] })
+        }
+    }
+
+    /// 時刻0から打ち上げ直せるようにする
+    pub fn restart(&mut self) {
+        self.slots.emitters = vec![Self::launcher(&self.settings)];
+        self.slots.restart();
+    }
+
+    /// 全ての枠が空いた状態の星
+    pub fn empty_instances(&self) -> Vec<StarInstance> {
+        self.slots.empty_instances()
+    }
+
+    /// 花火の星を入れる枠
+    pub fn slots(&self) -> &EmitterSystem {
+        &self.slots
+    }
+
+    pub fn slots_mut(&mut self) -> &mut EmitterSystem {
+        &mut self.slots
+    }
+
+    /// `at` で弾けて、あと `bursts` 回弾ける星を含む花火を飛び散らせる
+    fn explode(&mut self, instances: &mut [StarInstance], at: [f32; 2], bursts: u32) {
+        let first = bursts > 1 || self.settings.secondary_count == 0;
+        let count = if first {
+            self.settings.spark_count
+        } else {
+            (self.settings.spark_count as f32 * Self::SECONDARY_SCALE).ceil() as u32
+        };
+        let sparks = Emitter {
+            lifetime: if first { ValueRange::new(1.2, 2.0) } else { ValueRange::new(0.6, 1.0) },
+            speed: if first { ValueRange::new(0.3, 0.9) } else { ValueRange::new(0.1, 0.4) },
+            scale: if first { ValueRange::new(0.012, 0.025) } else { ValueRange::new(0.008, 0.015) },
+            rotation_speed: ValueRange::new(-4.0, 4.0),
+            color: PALETTE[self.rng.gen_range(0..PALETTE.len())],
+            ..Emitter::new(EmitterShape::Point(at))
+        };
+        let secondary = if bursts > 1 { self.settings.secondary_count.min(count) } else { 0 };
+        self.slots.emit(instances, &sparks, count - secondary);
+        self.slots.emit(instances, &Emitter { bursts: bursts - 1, ..sparks }, secondary);
+    }
+
+    /// `time` 秒までの dt 秒だけ花火を進める
+    /// 寿命の尽きた星を弾けさせ、打ち上げ、重力と空気の抵抗で減速させて、飛び散った星を薄くする
+    pub fn update(&mut self, instances: &mut [StarInstance], time: f32, dt: f32) {
+        let mut explosions = Vec::new();
+        for (star, life) in instances.iter().zip(self.slots.lives_mut()) {
+            if !life.is_alive() && life.bursts > 0 {
+                explosions.push((star.position, life.bursts));
+                life.bursts = 0;
+            }
+        }
+        self.slots.update(instances, time, dt);
+        for (at, bursts) in explosions {
+            self.explode(instances, at, bursts);
+        }
+
+        let fields = [ForceField::Gravity([0.0, -self.settings.gravity]), ForceField::Drag(self.settings.drag)];
+        forces::apply_forces(instances, &fields, time, dt);
+        for (star, life) in instances.iter_mut().zip(self.slots.lives()) {
+            if life.is_alive() && life.bursts == 0 {
+                // 寿命の終わりに向かって急に暗くなる
+                star.opacity = (1.0 - life.age / life.lifetime).clamp(0.0, 1.0).powi(2);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::emitter::StarLife;
+
+    fn settings() -> FireworksSettings {
+        FireworksSettings { spark_count: 40, secondary_count: 3, ..FireworksSettings::default() }
+    }
+
+    /// 寿命が尽きて、あと `bursts` 回弾ける星を枠の先頭に置く
+    fn spent_rocket(fireworks: &mut Fireworks, instances: &mut [StarInstance], at: [f32; 2], bursts: u32) {
+        instances[0].position = at;
+        fireworks.slots_mut().lives_mut()[0] = StarLife { age: 1.0, lifetime: 1.0, bursts };
+    }
+
+    fn alive<'a>(fireworks: &'a Fireworks, instances: &'a [StarInstance]) -> Vec<(&'a StarInstance, &'a StarLife)> {
+        instances.iter().zip(fireworks.slots.lives()).filter(|(_, life)| life.is_alive()).collect()
+    }
+
+    #[test]
+    fn rocket_explodes_into_spark_count_sparks() {
+        let mut fireworks = Fireworks::seeded(settings(), 512, 1);
+        let mut instances = fireworks.empty_instances();
+        spent_rocket(&mut fireworks, &mut instances, [0.2, 0.4], 2);
+        // dt が0なら打ち上げも年齢の進みも無い
+        fireworks.update(&mut instances, 0.0, 0.0);
+
+        let sparks = alive(&fireworks, &instances);
+        assert_eq!(sparks.len(), 40);
+        assert!(sparks.iter().all(|(spark, _)| spark.position == [0.2, 0.4]));
+        assert_eq!(sparks.iter().filter(|(_, life)| life.bursts == 1).count(), 3);
+        assert_eq!(sparks.iter().filter(|(_, life)| life.bursts == 0).count(), 37);
+    }
+
+    #[test]
+    fn secondary_sparks_explode_again_with_a_quarter_of_the_sparks() {
+        let mut fireworks = Fireworks::seeded(settings(), 512, 2);
+        let mut instances = fireworks.empty_instances();
+        spent_rocket(&mut fireworks, &mut instances, [0.0, 0.0], 2);
+        fireworks.update(&mut instances, 0.0, 0.0);
+        for life in fireworks.slots_mut().lives_mut().iter_mut().filter(|life| life.bursts == 1) {
+            life.age = life.lifetime;
+        }
+        fireworks.update(&mut instances, 0.0, 0.0);
+
+        // 1回目の火花37個に、3個が10個ずつ弾けた火花が加わり、もう弾けない
+        let sparks = alive(&fireworks, &instances);
+        assert_eq!(sparks.len(), 37 + 3 * 10);
+        assert!(sparks.iter().all(|(_, life)| life.bursts == 0));
+    }
+
+    #[test]
+    fn explosions_never_exceed_the_capacity() {
+        let mut fireworks = Fireworks::seeded(settings(), 30, 3);
+        let mut instances = fireworks.empty_instances();
+        for frame in 0..20 {
+            spent_rocket(&mut fireworks, &mut instances, [0.0, 0.0], 2);
+            fireworks.update(&mut instances, frame as f32 * 0.1, 0.1);
+            assert_eq!(instances.len(), 30);
+            assert!(alive(&fireworks, &instances).len() <= 30);
+        }
+    }
+
+    #[test]
+    fn strong_drag_does_not_reverse_sparks() {
+        let settings = FireworksSettings { gravity: 0.0, drag: 30.0, ..settings() };
+        let mut fireworks = Fireworks::seeded(settings, 512, 4);
+        let mut instances = fireworks.empty_instances();
+        spent_rocket(&mut fireworks, &mut instances, [0.0, 0.0], 1);
+        fireworks.update(&mut instances, 0.0, 0.0);
+        let before: Vec<[f32; 2]> = instances.iter().map(|star| star.speed).collect();
+        fireworks.update(&mut instances, 0.1, 0.1);
+        let lives = fireworks.slots.lives();
+        for ((star, speed), _) in instances.iter().zip(before).zip(lives).filter(|(_, life)| life.is_alive()) {
+            for axis in 0..2 {
+                assert!(star.speed[axis] * speed[axis] >= 0.0, "{:?} -> {:?}", speed, star.speed);
+                assert!(star.speed[axis].abs() <= speed[axis].abs());
+            }
+        }
+    }
+}
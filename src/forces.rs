@@ -0,0 +1,194 @@
+use crate::{simulation, vertex::instance::StarInstance};
+
+/// 星を加速する力の場
+/// 位置と距離は描画先の座標（-1.0..1.0）、強さは加速度（単位/秒²）で表す
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ForceField {
+    /// どこでも同じ向きに引く
+    Gravity([f32; 2]),
+    /// 星の速度を風の速度に近づける。`turbulence` の強さで場所と時間によって揺らぐ
+    Wind { velocity: [f32; 2], turbulence: f32 },
+    /// `center` へ引き寄せる（`strength` が負なら遠ざける）。`radius` の端に向かって0まで弱まる
+    Point { center: [f32; 2], strength: f32, radius: f32 },
+    /// `center` の周りを反時計回りに回す（`strength` が負なら時計回り）
+    Vortex { center: [f32; 2], strength: f32, radius: f32 },
+    /// 速さに比例して減速する（割合 k なら1秒で速度が e^-k 倍になる）
+    Drag(f32),
+}
+
+impl ForceField {
+    /// 引き寄せる力と渦の既定の半径
+    const DEFAULT_RADIUS: f32 = 1.0;
+    /// 風に流される速さ（1秒あたりに風との速度の差を詰める割合）
+    const WIND_DRAG: f32 = 0.5;
+    /// 乱流の模様の細かさ（1単位あたりのマス数）
+    const NOISE_SCALE: f32 = 2.0;
+    /// 乱流の模様が変わる速さ（1秒あたり）
+    const NOISE_SPEED: f32 = 0.5;
+
+    /// `gravity:<x>,<y>`
+    /// `wind:<x>,<y>[,<乱流の強さ>]`
+    /// `attractor:<x>,<y>,<強さ>[,<半径>]` `repulsor:<x>,<y>,<強さ>[,<半径>]`
+    /// `vortex:<x>,<y>,<強さ>[,<半径>]`
+    /// `drag:<割合>`
+    pub fn parse(value: &str) -> Option<Self> {
+        let (kind, args) = value.split_once(':')?;
+        let args = args.split(',').map(str::parse::<f32>).collect::<Result<Vec<_>, _>>().ok()?;
+        let radius = |args: &[f32]| match args {
+            [] => Some(Self::DEFAULT_RADIUS),
+            [radius] if *radius > 0.0 => Some(*radius),
+            _ => None,
+        };
+        match (kind, args.as_slice()) {
+            ("gravity", [x, y]) => Some(ForceField::Gravity([*x, *y])),
+            ("wind", [x, y]) => Some(ForceField::Wind { velocity: [*x, *y], turbulence: 0.0 }),
+            ("wind", [x, y, turbulence]) if *turbulence >= 0.0 => {
+                Some(ForceField::Wind { velocity: [*x, *y], turbulence: *turbulence })
+            }
+            ("attractor", [x, y, strength, rest @ ..]) => {
+                Some(ForceField::Point { center: [*x, *y], strength: *strength, radius: radius(rest)? })
+            }
+            ("repulsor", [x, y, strength, rest @ ..]) => {
+                Some(ForceField::Point { center: [*x, *y], strength: -*strength, radius: radius(rest)? })
+            }
+            ("vortex", [x, y, strength, rest @ ..]) => {
+                Some(ForceField::Vortex { center: [*x, *y], strength: *strength, radius: radius(rest)? })
+            }
+            ("drag", [drag]) if *drag >= 0.0 => Some(ForceField::Drag(*drag)),
+            _ => None,
+        }
+    }
+
+    /// `time` 秒の時に `star` が受ける加速度
+    fn acceleration(&self, star: &StarInstance, time: f32) -> [f32; 2] {
+        match *self {
+            ForceField::Gravity(acceleration) => acceleration,
+            ForceField::Wind { velocity, turbulence } => {
+                let [x, y] = star.position.map(|p| p * Self::NOISE_SCALE);
+                let t = time * Self::NOISE_SPEED;
+                // 別々の場所の雑音を x と y の揺らぎにする
+                let gust = [value_noise(x, y, t), value_noise(x + 31.7, y - 17.3, t)];
+                [
+                    (velocity[0] + gust[0] * turbulence - star.speed[0]) * Self::WIND_DRAG,
+                    (velocity[1] + gust[1] * turbulence - star.speed[1]) * Self::WIND_DRAG,
+                ]
+            }
+            ForceField::Point { center, strength, radius } => {
+                let (dx, dy) = (center[0] - star.position[0], center[1] - star.position[1]);
+                let distance = (dx * dx + dy * dy).sqrt();
+                // 中心の真上の星は向きが決まらないので動かさない
+                if distance >= radius || distance < 1e-4 {
+                    return [0.0, 0.0];
+                }
+                let accel = strength * (1.0 - distance / radius) / distance;
+                [dx * accel, dy * accel]
+            }
+            ForceField::Vortex { center, strength, radius } => {
+                let (dx, dy) = (star.position[0] - center[0], star.position[1] - center[1]);
+                let distance = (dx * dx + dy * dy).sqrt();
+                if distance >= radius || distance < 1e-4 {
+                    return [0.0, 0.0];
+                }
+                let accel = strength * (1.0 - distance / radius) / distance;
+                [-dy * accel, dx * accel]
+            }
+            // 減速は加速度にすると dt が大きい時に向きが逆になるので、`apply_forces` で掛ける
+            ForceField::Drag(_) => [0.0, 0.0],
+        }
+    }
+
+    /// 速度を減らす割合
+    fn drag(&self) -> f32 {
+        match *self {
+            ForceField::Drag(drag) => drag,
+            _ => 0.0,
+        }
+    }
+}
+
+/// 格子点ごとに決まる -1.0..1.0 の値
+fn hash(x: i32, y: i32, z: i32) -> f32 {
+    let mut h = (x as u32).wrapping_mul(0x8da6_b343)
+        ^ (y as u32).wrapping_mul(0xd816_3841)
+        ^ (z as u32).wrapping_mul(0xcb1a_b31f);
+    h ^= h >> 13;
+    h = h.wrapping_mul(0x5bd1_e995);
+    h ^= h >> 15;
+    h as f32 / u32::MAX as f32 * 2.0 - 1.0
+}
+
+/// 格子点の値を滑らかに補間した -1.0..1.0 の雑音
+fn value_noise(x: f32, y: f32, z: f32) -> f32 {
+    let (x0, y0, z0) = (x.floor(), y.floor(), z.floor());
+    let smooth = |t: f32| t * t * (3.0 - 2.0 * t);
+    let (fx, fy, fz) = (smooth(x - x0), smooth(y - y0), smooth(z - z0));
+    let (ix, iy, iz) = (x0 as i32, y0 as i32, z0 as i32);
+    let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+    let plane = |z: i32| {
+        lerp(
+            lerp(hash(ix, iy, z), hash(ix + 1, iy, z), fx),
+            lerp(hash(ix, iy + 1, z), hash(ix + 1, iy + 1, z), fx),
+            fy,
+        )
+    };
+    lerp(plane(iz), plane(iz + 1), fz)
+}
+
+/// 力の場で星を dt 秒だけ加速する
+pub fn apply_forces(instances: &mut [StarInstance], fields: &[ForceField], time: f32, dt: f32) {
+    if fields.is_empty() {
+        return;
+    }
+    let damping = (-fields.iter().map(ForceField::drag).sum::<f32>() * dt).exp();
+    for star in instances.iter_mut() {
+        let mut acceleration = [0.0, 0.0];
+        for field in fields {
+            let [ax, ay] = field.acceleration(star, time);
+            acceleration[0] += ax;
+            acceleration[1] += ay;
+        }
+        star.speed[0] = (star.speed[0] + acceleration[0] * dt) * damping;
+        star.speed[1] = (star.speed[1] + acceleration[1] * dt) * damping;
+        simulation::limit_speed(star);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn moving_star(speed: [f32; 2]) -> StarInstance {
+        StarInstance { speed, ..bytemuck::Zeroable::zeroed() }
+    }
+
+    #[test]
+    fn drag_decays_exponentially() {
+        let mut instances = [moving_star([1.0, -0.5])];
+        apply_forces(&mut instances, &[ForceField::Drag(2.0)], 0.0, 0.5);
+        let expected = (-1.0f32).exp();
+        assert!((instances[0].speed[0] - expected).abs() < 1e-6);
+        assert!((instances[0].speed[1] + 0.5 * expected).abs() < 1e-6);
+    }
+
+    #[test]
+    fn strong_drag_never_reverses_the_velocity() {
+        let mut instances = [moving_star([1.0, 1.0])];
+        // 加速度として足すと速度が -2 倍になって発散する強さと時間
+        for _ in 0..10 {
+            apply_forces(&mut instances, &[ForceField::Drag(30.0)], 0.0, 0.1);
+            assert!(instances[0].speed.iter().all(|s| (0.0..1.0).contains(s)), "{:?}", instances[0].speed);
+        }
+    }
+
+    #[test]
+    fn drag_also_slows_the_acceleration_of_other_fields() {
+        let mut instances = [moving_star([0.0, 0.0])];
+        let fields = [ForceField::Gravity([0.0, -1.0]), ForceField::Drag(1.0)];
+        for _ in 0..200 {
+            apply_forces(&mut instances, &fields, 0.0, 0.05);
+        }
+        // 重力と抵抗が釣り合う速さで落ち続ける
+        let terminal = -0.05 * (-0.05f32).exp() / (1.0 - (-0.05f32).exp());
+        assert!((instances[0].speed[1] - terminal).abs() < 1e-3, "{}", instances[0].speed[1]);
+    }
+}
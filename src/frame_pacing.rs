@@ -0,0 +1,127 @@
+use std::time::{Duration, Instant};
+
+use winit::event_loop::{ActiveEventLoop, ControlFlow};
+use winit::window::Window;
+
+/// フレームの描画タイミングの種類
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum FramePacing {
+    /// 垂直同期に合わせて連続描画する
+    #[default]
+    Vsync,
+    /// 指定したFPSを上限として描画する
+    FpsCap(u32),
+    /// 何か変化があった時だけ描画する
+    OnDemand,
+}
+
+impl FramePacing {
+    /// `vsync` / `fps:60` / `idle` の形式の文字列から読み取る
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "vsync" => Some(FramePacing::Vsync),
+            "idle" | "on-demand" => Some(FramePacing::OnDemand),
+            _ => value
+                .strip_prefix("fps:")
+                .and_then(|fps| fps.parse::<u32>().ok())
+                .filter(|fps| *fps > 0)
+                .map(FramePacing::FpsCap),
+        }
+    }
+
+    /// 描画タイミングに合わせたサーフェスのプレゼントモード
+    pub fn present_mode(&self) -> wgpu::PresentMode {
+        match self {
+            FramePacing::Vsync | FramePacing::OnDemand => wgpu::PresentMode::AutoVsync,
+            // 上限はイベントループ側で制御するので、垂直同期で待たされないようにする
+            FramePacing::FpsCap(_) => wgpu::PresentMode::AutoNoVsync,
+        }
+    }
+}
+
+/// 描画タイミングに応じてイベントループの待機と再描画要求を管理する
+#[derive(Debug, Default)]
+pub struct FramePacer {
+    pub mode: FramePacing,
+    next_frame: Option<Instant>,
+    dirty: bool,
+}
+
+impl FramePacer {
+    pub fn new(mode: FramePacing) -> Self {
+        Self {
+            mode,
+            next_frame: None,
+            // 初回は必ず描画する
+            dirty: true,
+        }
+    }
+
+    /// 画面に変化があったことを記録する（OnDemandモードで再描画される）
+    pub fn mark_dirty(&mut self) {
+        self.dirty = true;
+    }
+
+    /// フレームを描画し終えた時に呼ぶ
+    pub fn frame_presented(&mut self) {
+        self.dirty = false;
+    }
+
+    /// イベントが一通り処理された後に呼び、次の描画の要求と待機方法を決める
+    pub fn about_to_wait(&mut self, event_loop: &ActiveEventLoop, window: &Window) {
+        match self.mode {
+            FramePacing::Vsync => {
+                // 待機はサーフェスの取得で垂直同期に任せる
+                window.request_redraw();
+                event_loop.set_control_flow(ControlFlow::Wait);
+            }
+            FramePacing::FpsCap(fps) => {
+                let interval = Duration::from_secs_f64(1.0 / fps as f64);
+                let now = Instant::now();
+                let next_frame = *self.next_frame.get_or_insert(now);
+                if now >= next_frame {
+                    window.request_redraw();
+                    // 大きく遅れた場合は追いつこうとせずに現在時刻から数え直す
+                    let next = next_frame + interval;
+                    self.next_frame = Some(if next < now { now + interval } else { next });
+                }
+                event_loop.set_control_flow(ControlFlow::WaitUntil(
+                    self.next_frame.unwrap_or(now + interval),
+                ));
+            }
+            FramePacing::OnDemand => {
+                if self.dirty {
+                    window.request_redraw();
+                }
+                event_loop.set_control_flow(ControlFlow::Wait);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_each_mode() {
+        assert_eq!(FramePacing::parse("vsync"), Some(FramePacing::Vsync));
+        assert_eq!(FramePacing::parse("idle"), Some(FramePacing::OnDemand));
+        assert_eq!(FramePacing::parse("on-demand"), Some(FramePacing::OnDemand));
+        assert_eq!(FramePacing::parse("fps:144"), Some(FramePacing::FpsCap(144)));
+    }
+
+    #[test]
+    fn rejects_invalid_fps() {
+        for value in ["fps:0", "fps:-30", "fps:abc", "fps:", "60", "VSYNC", ""] {
+            assert_eq!(FramePacing::parse(value), None, "{:?}", value);
+        }
+    }
+
+    #[test]
+    fn fps_cap_does_not_wait_for_vsync() {
+        assert_eq!(FramePacing::FpsCap(30).present_mode(), wgpu::PresentMode::AutoNoVsync);
+        assert_eq!(FramePacing::Vsync.present_mode(), wgpu::PresentMode::AutoVsync);
+        assert_eq!(FramePacing::OnDemand.present_mode(), wgpu::PresentMode::AutoVsync);
+    }
+}
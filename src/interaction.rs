@@ -0,0 +1,188 @@
+use rand::Rng;
+
+use crate::{
+    emitter::{Emitter, EmitterShape, StarLife, ValueRange},
+    simulation,
+    vertex::instance::StarInstance,
+};
+
+/// カーソルが星に及ぼす力
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum PointerForce {
+    /// カーソルへ引き寄せる
+    #[default]
+    Attract,
+    /// カーソルから遠ざける
+    Repel,
+    /// 力を及ぼさない（クリックでの追加と削除だけ）
+    None,
+}
+
+impl PointerForce {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "attract" => Some(PointerForce::Attract),
+            "repel" => Some(PointerForce::Repel),
+            "none" => Some(PointerForce::None),
+            _ => None,
+        }
+    }
+}
+
+/// マウスでの操作の設定
+/// 位置と距離は描画先の座標（-1.0..1.0）で表す
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct InteractionSettings {
+    pub force: PointerForce,
+    /// 力が届く半径
+    pub radius: f32,
+    /// カーソルの位置での加速度（単位/秒²）。半径の端に向かって0まで弱まる
+    pub strength: f32,
+    /// 左クリックで生まれる星の数
+    pub burst_count: u32,
+    /// 右クリックで星を消す半径
+    pub remove_radius: f32,
+    /// 指でなぞった時に一緒に動く星の半径
+    pub drag_radius: f32,
+}
+
+impl Default for InteractionSettings {
+    fn default() -> Self {
+        Self {
+            force: PointerForce::Attract,
+            radius: 0.5,
+            strength: 2.0,
+            burst_count: 20,
+            remove_radius: 0.15,
+            drag_radius: 0.2,
+        }
+    }
+}
+
+/// 星空の表示の拡大とずらし
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct View {
+    pub zoom: f32,
+    pub offset: [f32; 2],
+}
+
+impl Default for View {
+    fn default() -> Self {
+        Self {
+            zoom: 1.0,
+            offset: [0.0, 0.0],
+        }
+    }
+}
+
+impl View {
+    const MIN_ZOOM: f32 = 0.25;
+    const MAX_ZOOM: f32 = 8.0;
+
+    /// 描画先の座標を星空の座標に直す
+    pub fn to_scene(&self, point: [f32; 2]) -> [f32; 2] {
+        [
+            (point[0] - self.offset[0]) / self.zoom,
+            (point[1] - self.offset[1]) / self.zoom,
+        ]
+    }
+
+    /// 描画先の `center` の位置を動かさずに `factor` 倍に拡大する
+    pub fn zoom_about(&mut self, center: [f32; 2], factor: f32) {
+        let zoom = (self.zoom * factor).clamp(Self::MIN_ZOOM, Self::MAX_ZOOM);
+        let factor = zoom / self.zoom;
+        self.offset = [
+            center[0] - (center[0] - self.offset[0]) * factor,
+            center[1] - (center[1] - self.offset[1]) * factor,
+        ];
+        self.zoom = zoom;
+    }
+}
+
+/// カーソルの力で星を dt 秒だけ加速する
+pub fn apply_pointer_force(instances: &mut [StarInstance], pointer: [f32; 2], settings: &InteractionSettings, dt: f32) {
+    let sign = match settings.force {
+        PointerForce::Attract => 1.0,
+        PointerForce::Repel => -1.0,
+        PointerForce::None => return,
+    };
+    for star in instances.iter_mut() {
+        let (dx, dy) = (pointer[0] - star.position[0], pointer[1] - star.position[1]);
+        let distance = (dx * dx + dy * dy).sqrt();
+        // カーソルの真上の星は向きが決まらないので動かさない
+        if distance >= settings.radius || distance < 1e-4 {
+            continue;
+        }
+        let accel = sign * settings.strength * (1.0 - distance / settings.radius) * dt / distance;
+        star.speed[0] += dx * accel;
+        star.speed[1] += dy * accel;
+        simulation::limit_speed(star);
+    }
+}
+
+/// `at` から四方へ飛び散る星を `count` 個作る
+pub fn spawn_burst(at: [f32; 2], count: u32, rng: &mut impl Rng) -> Vec<StarInstance> {
+    StarInstance::new_vec_with_rng(count, rng)
+        .into_iter()
+        .map(|star| {
+            let angle = rng.gen_range(0.0..std::f32::consts::TAU);
+            let speed = rng.gen_range(0.2..0.6);
+            StarInstance {
+                position: at,
+                speed: [angle.cos() * speed, angle.sin() * speed],
+                ..star
+            }
+        })
+        .collect()
+}
+
+/// `at` から星を飛び散らせるエミッター
+/// エミッターや花火の枠に入れる時に使い、寿命が尽きると枠を空ける
+pub fn burst_emitter(at: [f32; 2]) -> Emitter {
+    Emitter {
+        speed: ValueRange::new(0.2, 0.6),
+        ..Emitter::new(EmitterShape::Point(at))
+    }
+}
+
+/// `positions` が `at` から `radius` 以内にある星を取り除き、取り除いた数を返す
+/// `positions` は `instances` と同じ順の、今の星の位置
+pub fn remove_near(instances: &mut Vec<StarInstance>, positions: &[[f32; 2]], at: [f32; 2], radius: f32) -> usize {
+    let before = instances.len();
+    let mut positions = positions.iter();
+    instances.retain(|_| {
+        let [x, y] = positions.next().copied().unwrap_or([f32::INFINITY; 2]);
+        (x - at[0]).powi(2) + (y - at[1]).powi(2) > radius * radius
+    });
+    before - instances.len()
+}
+
+/// `positions` が `at` から `radius` 以内にある星の寿命を終わらせ、終わらせた数を返す
+/// `lives` はエミッターや花火の枠と同じ順の年齢と寿命。星を取り除かないので、枠の並びは変わらない
+pub fn expire_near(lives: &mut [StarLife], positions: &[[f32; 2]], at: [f32; 2], radius: f32) -> usize {
+    let mut expired = 0;
+    for (life, [x, y]) in lives.iter_mut().zip(positions) {
+        if life.is_alive() && (x - at[0]).powi(2) + (y - at[1]).powi(2) <= radius * radius {
+            life.lifetime = life.age;
+            // 花火の星は寿命が尽きると弾けるので、弾けずに消えるようにする
+            life.bursts = 0;
+            expired += 1;
+        }
+    }
+    expired
+}
+
+/// `positions` が `from` から `radius` 以内にある星を `to` の方へ同じだけ動かし、動かした数を返す
+/// `positions` は `instances` と同じ順の、今の星の位置
+pub fn drag(instances: &mut [StarInstance], positions: &[[f32; 2]], from: [f32; 2], to: [f32; 2], radius: f32) -> usize {
+    let delta = [to[0] - from[0], to[1] - from[1]];
+    let mut moved = 0;
+    for (star, [x, y]) in instances.iter_mut().zip(positions) {
+        if (x - from[0]).powi(2) + (y - from[1]).powi(2) <= radius * radius {
+            star.position[0] += delta[0];
+            star.position[1] += delta[1];
+            moved += 1;
+        }
+    }
+    moved
+}
@@ -0,0 +1,188 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use winit::keyboard::KeyCode;
+
+/// キーで実行できる操作
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Command {
+    /// 一時停止と再開
+    TogglePause,
+    /// 一時停止して1コマ進める
+    Step,
+    /// 時間の進みを速くする
+    SpeedUp,
+    /// 時間の進みを遅くする
+    SlowDown,
+    /// 星空を作り直す
+    Regenerate,
+    ToggleFullscreen,
+    /// 描画結果をPNGに保存する
+    Screenshot,
+    /// 統計情報の表示を切り替える
+    ToggleOverlay,
+    Quit,
+}
+
+impl Command {
+    const ALL: [Command; 9] = [
+        Command::TogglePause,
+        Command::Step,
+        Command::SpeedUp,
+        Command::SlowDown,
+        Command::Regenerate,
+        Command::ToggleFullscreen,
+        Command::Screenshot,
+        Command::ToggleOverlay,
+        Command::Quit,
+    ];
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|command| command.name() == value)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Command::TogglePause => "pause",
+            Command::Step => "step",
+            Command::SpeedUp => "speed-up",
+            Command::SlowDown => "slow-down",
+            Command::Regenerate => "regenerate",
+            Command::ToggleFullscreen => "fullscreen",
+            Command::Screenshot => "screenshot",
+            Command::ToggleOverlay => "overlay",
+            Command::Quit => "quit",
+        }
+    }
+}
+
+/// 設定ファイルで使えるキー（名前は winit の `KeyCode` と同じ）
+const KEYS: [KeyCode; 76] = [
+    KeyCode::KeyA, KeyCode::KeyB, KeyCode::KeyC, KeyCode::KeyD, KeyCode::KeyE, KeyCode::KeyF, KeyCode::KeyG,
+    KeyCode::KeyH, KeyCode::KeyI, KeyCode::KeyJ, KeyCode::KeyK, KeyCode::KeyL, KeyCode::KeyM, KeyCode::KeyN,
+    KeyCode::KeyO, KeyCode::KeyP, KeyCode::KeyQ, KeyCode::KeyR, KeyCode::KeyS, KeyCode::KeyT, KeyCode::KeyU,
+    KeyCode::KeyV, KeyCode::KeyW, KeyCode::KeyX, KeyCode::KeyY, KeyCode::KeyZ,
+    KeyCode::Digit0, KeyCode::Digit1, KeyCode::Digit2, KeyCode::Digit3, KeyCode::Digit4,
+    KeyCode::Digit5, KeyCode::Digit6, KeyCode::Digit7, KeyCode::Digit8, KeyCode::Digit9,
+    KeyCode::F1, KeyCode::F2, KeyCode::F3, KeyCode::F4, KeyCode::F5, KeyCode::F6,
+    KeyCode::F7, KeyCode::F8, KeyCode::F9, KeyCode::F10, KeyCode::F11, KeyCode::F12,
+    KeyCode::Space, KeyCode::Enter, KeyCode::Escape, KeyCode::Tab, KeyCode::Backspace,
+    KeyCode::Delete, KeyCode::Insert, KeyCode::Home, KeyCode::End, KeyCode::PageUp, KeyCode::PageDown,
+    KeyCode::ArrowUp, KeyCode::ArrowDown, KeyCode::ArrowLeft, KeyCode::ArrowRight,
+    KeyCode::Period, KeyCode::Comma, KeyCode::Slash, KeyCode::Backslash, KeyCode::Semicolon,
+    KeyCode::Quote, KeyCode::Backquote, KeyCode::BracketLeft, KeyCode::BracketRight,
+    KeyCode::Minus, KeyCode::Equal, KeyCode::PrintScreen, KeyCode::Pause,
+];
+
+fn parse_key(value: &str) -> Option<KeyCode> {
+    KEYS.into_iter().find(|key| format!("{:?}", key) == value)
+}
+
+/// キーと操作の対応
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeyBindings {
+    bindings: HashMap<KeyCode, Command>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            bindings: HashMap::from([
+                (KeyCode::Space, Command::TogglePause),
+                (KeyCode::Period, Command::Step),
+                (KeyCode::BracketRight, Command::SpeedUp),
+                (KeyCode::BracketLeft, Command::SlowDown),
+                (KeyCode::KeyR, Command::Regenerate),
+                (KeyCode::F11, Command::ToggleFullscreen),
+                (KeyCode::F12, Command::Screenshot),
+                (KeyCode::F3, Command::ToggleOverlay),
+                (KeyCode::Escape, Command::Quit),
+            ]),
+        }
+    }
+}
+
+impl KeyBindings {
+    /// `<キー> = <操作>` を1行ずつ並べた設定ファイルを読む（`#` から行末まではコメント）
+    /// ファイルに書いたキーだけが有効になる。読めない行は警告して飛ばす
+    ///
+    /// ```text
+    /// Space = pause
+    /// KeyQ = quit
+    /// ```
+    pub fn load(path: &Path) -> std::io::Result<Self> {
+        Ok(Self::parse(&std::fs::read_to_string(path)?))
+    }
+
+    pub fn parse(source: &str) -> Self {
+        let mut bindings = HashMap::new();
+        for (number, line) in source.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let Some((key, command)) = line.split_once('=') else {
+                eprintln!("Invalid key binding on line {}: {:?}", number + 1, line);
+                continue;
+            };
+            match (parse_key(key.trim()), Command::parse(command.trim())) {
+                (Some(key), Some(command)) => {
+                    bindings.insert(key, command);
+                }
+                (None, _) => eprintln!("Unknown key on line {}: {:?}", number + 1, key.trim()),
+                (_, None) => eprintln!("Unknown command on line {}: {:?}", number + 1, command.trim()),
+            }
+        }
+        Self { bindings }
+    }
+
+    pub fn lookup(&self, key: KeyCode) -> Option<Command> {
+        self.bindings.get(&key).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn command_names_round_trip() {
+        for command in Command::ALL {
+            assert_eq!(Command::parse(command.name()), Some(command));
+        }
+        assert_eq!(Command::parse("Pause"), None);
+    }
+
+    #[test]
+    fn parses_keys_by_winit_name() {
+        assert_eq!(parse_key("KeyQ"), Some(KeyCode::KeyQ));
+        assert_eq!(parse_key("F12"), Some(KeyCode::F12));
+        assert_eq!(parse_key("BracketLeft"), Some(KeyCode::BracketLeft));
+        assert_eq!(parse_key("q"), None);
+        assert_eq!(parse_key("Numpad0"), None);
+    }
+
+    #[test]
+    fn skips_comments_and_blank_lines() {
+        let bindings = KeyBindings::parse("# 一時停止\n\n  KeyP = pause  # 行末のコメント\n#KeyQ = quit\n");
+        assert_eq!(bindings.lookup(KeyCode::KeyP), Some(Command::TogglePause));
+        assert_eq!(bindings.lookup(KeyCode::KeyQ), None);
+        assert_eq!(bindings.bindings.len(), 1);
+    }
+
+    #[test]
+    fn skips_invalid_lines() {
+        let bindings = KeyBindings::parse("KeyP pause\nKeyX = explode\nHyper = quit\n= quit\nKeyQ = quit\n");
+        assert_eq!(bindings, KeyBindings { bindings: HashMap::from([(KeyCode::KeyQ, Command::Quit)]) });
+    }
+
+    #[test]
+    fn file_replaces_defaults_and_later_lines_win() {
+        let bindings = KeyBindings::parse("Space = screenshot\nKeyS = step\nKeyS = slow-down\n");
+        assert_eq!(KeyBindings::default().lookup(KeyCode::Space), Some(Command::TogglePause));
+        assert_eq!(bindings.lookup(KeyCode::Space), Some(Command::Screenshot));
+        assert_eq!(bindings.lookup(KeyCode::KeyS), Some(Command::SlowDown));
+        // ファイルに書かなかった既定のキーは効かない
+        assert_eq!(bindings.lookup(KeyCode::Escape), None);
+    }
+}
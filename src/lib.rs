@@ -0,0 +1,20 @@
+pub mod benchmark;
+pub mod boids;
+pub mod clock;
+pub mod collision;
+pub mod config;
+pub mod curves;
+pub mod emitter;
+pub mod fireworks;
+pub mod forces;
+pub mod ctx;
+pub mod frame_pacing;
+pub mod interaction;
+pub mod keybindings;
+pub mod overlay;
+pub mod simulation;
+pub mod spatial;
+pub mod texture;
+pub mod touch;
+pub mod vertex;
+pub mod time_uniform;
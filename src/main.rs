@@ -1,38 +1,180 @@
-mod ctx;
-mod vertex;
-mod time_uniform;
-
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
 
-use ctx::ctx_traits::WgpuCtxBase;
-use ctx::wgpu_ctx::WgpuCtx;
-use ctx::wgpu_star_ctx::{self, WgpuStarCtx};
+use wasm_only_draws::benchmark;
+use wasm_only_draws::config::AppConfig;
+use wasm_only_draws::ctx::sdf_star::StarRenderMode;
+use wasm_only_draws::ctx::sprite::SpriteAtlas;
+use wasm_only_draws::ctx::wgpu_ctx::WgpuCtx;
+use wasm_only_draws::ctx::wgpu_star_ctx::WgpuStarCtx;
+use wasm_only_draws::emitter::EmitterSystem;
+use wasm_only_draws::fireworks::Fireworks;
+use wasm_only_draws::frame_pacing::FramePacer;
+use wasm_only_draws::interaction::PointerForce;
+use wasm_only_draws::keybindings::Command;
+use wasm_only_draws::simulation::{EdgeMode, SimulationMode};
+use wasm_only_draws::touch::{TouchGesture, TouchTracker};
 use winit::application::ApplicationHandler;
-use winit::event::WindowEvent;
-use winit::event_loop::{ActiveEventLoop, ControlFlow, EventLoop};
-use winit::window::{Window, WindowId};
+use winit::dpi::{PhysicalPosition, PhysicalSize};
+use winit::event::{ElementState, KeyEvent, MouseButton, Touch, WindowEvent};
+use winit::event_loop::{ActiveEventLoop, EventLoop};
+use winit::keyboard::PhysicalKey;
+use winit::window::{Fullscreen, Window, WindowId, WindowLevel};
 
 #[derive(Default)]
 pub struct App<'window> {
     window: Option<Arc<Window>>,
     wgpu_ctx: Option<WgpuCtx<'window>>,
     wgpu_star_ctx: Option<WgpuStarCtx<'window>>,
+    pacer: FramePacer,
+    config: AppConfig,
+    touch: TouchTracker,
 }
 
+impl App<'_> {
+    pub fn new(config: AppConfig) -> Self {
+        Self {
+            pacer: FramePacer::new(config.pacing),
+            config,
+            ..Default::default()
+        }
+    }
+
+    /// キーに割り当てた操作を実行する
+    fn run_command(&mut self, event_loop: &ActiveEventLoop, command: Command) {
+        let (Some(wgpu_star_ctx), Some(window)) = (self.wgpu_star_ctx.as_mut(), self.window.as_ref()) else {
+            return;
+        };
+        match command {
+            Command::TogglePause => wgpu_star_ctx.clock.toggle_pause(),
+            Command::Step => wgpu_star_ctx.clock.step(),
+            Command::SpeedUp => wgpu_star_ctx.clock.speed_up(),
+            Command::SlowDown => wgpu_star_ctx.clock.slow_down(),
+            Command::Regenerate => match (wgpu_star_ctx.fireworks.take(), wgpu_star_ctx.emitters.take()) {
+                (Some(fireworks), _) => wgpu_star_ctx.set_fireworks(fireworks),
+                (None, Some(emitters)) => wgpu_star_ctx.set_emitters(emitters),
+                (None, None) => wgpu_star_ctx.set_star_count(wgpu_star_ctx.instance_count),
+            },
+            Command::ToggleFullscreen => {
+                let fullscreen = match window.fullscreen() {
+                    Some(_) => None,
+                    None => Some(Fullscreen::Borderless(None)),
+                };
+                window.set_fullscreen(fullscreen);
+            }
+            Command::Screenshot => {
+                let seconds = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs());
+                wgpu_star_ctx.request_screenshot(PathBuf::from(format!("screenshot-{}.png", seconds)));
+            }
+            Command::ToggleOverlay => wgpu_star_ctx.overlay.toggle(),
+            Command::Quit => event_loop.exit(),
+        }
+        self.pacer.mark_dirty();
+    }
+}
+
+
+/// ピクセル座標（左上が原点）を描画先の座標（-1.0..1.0）に直す
+fn to_ndc(position: PhysicalPosition<f64>, size: PhysicalSize<u32>) -> [f32; 2] {
+    [
+        (position.x / size.width.max(1) as f64 * 2.0 - 1.0) as f32,
+        (1.0 - position.y / size.height.max(1) as f64 * 2.0) as f32,
+    ]
+}
 
 impl<'window> ApplicationHandler for App<'window> {
     fn resumed(&mut self, event_loop: &ActiveEventLoop) {
         if self.window.is_none() {
-            let win_attr = Window::default_attributes().with_title("wgpu winit example");
+            let mut win_attr = Window::default_attributes().with_title("wgpu winit example");
+            if self.config.transparent_window {
+                // デスクトップの上に重ねるため、枠を消して最前面に置く
+                win_attr = win_attr
+                    .with_transparent(true)
+                    .with_decorations(false)
+                    .with_window_level(WindowLevel::AlwaysOnTop)
+                    .with_maximized(true);
+            }
             // use Arc.
             let window = Arc::new(
                 event_loop
                     .create_window(win_attr)
                     .expect("create window err."),
             );
+            if self.config.click_through {
+                if let Err(err) = window.set_cursor_hittest(false) {
+                    eprintln!("Click-through is not supported: {:?}", err);
+                }
+            }
             self.window = Some(window.clone());
             self.wgpu_ctx = Some(WgpuCtx::new(window.clone()));
-            self.wgpu_star_ctx = Some(WgpuStarCtx::new(window.clone()));
+            let mut wgpu_star_ctx = WgpuStarCtx::new(window.clone());
+            wgpu_star_ctx.set_present_mode(self.pacer.mode.present_mode());
+            if self.config.transparent_window {
+                wgpu_star_ctx.use_compositing_alpha();
+            }
+            wgpu_star_ctx.simulation = self.config.simulation;
+            wgpu_star_ctx.interaction = self.config.interaction;
+            wgpu_star_ctx.edges = self.config.edges;
+            wgpu_star_ctx.collisions = self.config.collisions;
+            wgpu_star_ctx.forces = self.config.forces.clone();
+            wgpu_star_ctx.boids = self.config.boids;
+            wgpu_star_ctx.curves = self.config.curves.clone();
+            if let Some(settings) = self.config.fireworks {
+                wgpu_star_ctx.set_fireworks(Fireworks::new(settings, self.config.emitter_capacity));
+                if self.config.edges == EdgeMode::Wrap {
+                    // 落ちた火花が上から出てこないようにする
+                    wgpu_star_ctx.edges = EdgeMode::Vanish;
+                }
+            } else if !self.config.emitters.is_empty() {
+                wgpu_star_ctx.set_emitters(EmitterSystem::new(self.config.emitters.clone(), self.config.emitter_capacity));
+            }
+            if self.config.interactive && self.config.interaction.force != PointerForce::None {
+                // カーソルの力で速度が変わるので、CPUで動かす
+                wgpu_star_ctx.simulation = SimulationMode::Cpu;
+            }
+            let needs_cpu = self.config.collisions
+                || self.config.edges != EdgeMode::Wrap
+                || !self.config.forces.is_empty()
+                || self.config.boids.is_some()
+                || !self.config.emitters.is_empty()
+                || self.config.fireworks.is_some()
+                || self.config.curves.is_some();
+            if needs_cpu {
+                // 衝突・跳ね返り・力の場・群れ・エミッター・花火・寿命の曲線はCPUでしか計算しない
+                wgpu_star_ctx.simulation = SimulationMode::Cpu;
+            }
+            wgpu_star_ctx.set_sample_count(self.config.msaa_samples);
+            wgpu_star_ctx.set_background(self.config.background.clone());
+            wgpu_star_ctx.set_bloom(self.config.bloom);
+            wgpu_star_ctx.set_trails(self.config.trails);
+            wgpu_star_ctx.set_ordering(self.config.depth_order);
+            wgpu_star_ctx.render_mode = self.config.render_mode;
+            if self.config.render_mode == StarRenderMode::Sprite {
+                let (columns, rows) = self.config.sprite_grid;
+                match self.config.sprite_atlas.as_deref().map(|path| SpriteAtlas::load(path, columns, rows)) {
+                    Some(Ok(atlas)) => wgpu_star_ctx.set_sprite_atlas(&atlas),
+                    Some(Err(err)) => {
+                        eprintln!("Failed to load sprite atlas: {}", err);
+                        wgpu_star_ctx.render_mode = StarRenderMode::Mesh;
+                    }
+                    None => {
+                        eprintln!("--render=sprite needs --sprite-atlas=<PNG>");
+                        wgpu_star_ctx.render_mode = StarRenderMode::Mesh;
+                    }
+                }
+            }
+            if wgpu_star_ctx.render_mode == StarRenderMode::Sprite
+                && self.config.curves.as_ref().is_some_and(|curves| curves.color.is_some())
+            {
+                // スプライトはアトラスの色で描くので、星の色は使わない
+                eprintln!("The color curve has no effect on sprites, which keep the atlas colors");
+            }
+            wgpu_star_ctx.sdf.style = self.config.sdf_style;
+            wgpu_star_ctx.warp.camera = self.config.camera;
+            wgpu_star_ctx.lit.light = self.config.light;
+            self.wgpu_star_ctx = Some(wgpu_star_ctx);
+            self.pacer.mark_dirty();
         }
     }
 
@@ -51,35 +193,108 @@ impl<'window> ApplicationHandler for App<'window> {
                     window.request_redraw();
                 } */
 
-               if let (Some(wgpu_star_ctx),Some(window)) = (self.wgpu_star_ctx.as_mut(),self.window.as_ref()) {
+               if let Some(wgpu_star_ctx) = self.wgpu_star_ctx.as_mut() {
                    wgpu_star_ctx.resize((new_size.width, new_size.height));
-                   window.request_redraw();
+                   self.pacer.mark_dirty();
                }
             }
+            WindowEvent::Occluded(false) | WindowEvent::ScaleFactorChanged { .. } => {
+                self.pacer.mark_dirty();
+            }
+            WindowEvent::KeyboardInput {
+                event: KeyEvent {
+                    physical_key: PhysicalKey::Code(key),
+                    state: ElementState::Pressed,
+                    repeat: false,
+                    ..
+                },
+                ..
+            } => {
+                if let Some(command) = self.config.keybindings.lookup(key) {
+                    self.run_command(event_loop, command);
+                }
+            }
+            WindowEvent::CursorMoved { position, .. } if self.config.interactive => {
+                if let (Some(wgpu_star_ctx), Some(window)) = (self.wgpu_star_ctx.as_mut(), self.window.as_ref()) {
+                    let position = to_ndc(position, window.inner_size());
+                    wgpu_star_ctx.pointer = Some(wgpu_star_ctx.view.to_scene(position));
+                    self.pacer.mark_dirty();
+                }
+            }
+            WindowEvent::CursorLeft { .. } => {
+                if let Some(wgpu_star_ctx) = self.wgpu_star_ctx.as_mut() {
+                    wgpu_star_ctx.pointer = None;
+                }
+            }
+            WindowEvent::MouseInput { state: ElementState::Pressed, button, .. } if self.config.interactive => {
+                // 左クリックで星を追加し、右クリックで近くの星を消す
+                if let Some(wgpu_star_ctx) = self.wgpu_star_ctx.as_mut() {
+                    let Some(pointer) = wgpu_star_ctx.pointer else {
+                        return;
+                    };
+                    match button {
+                        MouseButton::Left => wgpu_star_ctx.spawn_burst(pointer),
+                        MouseButton::Right => {
+                            wgpu_star_ctx.remove_stars_near(pointer);
+                        }
+                        _ => (),
+                    }
+                    self.pacer.mark_dirty();
+                }
+            }
+            WindowEvent::Touch(Touch { id, phase, location, .. }) if self.config.interactive => {
+                // タップで星を追加し、1本の指でなぞると近くの星が付いてきて、2本の指で拡大縮小する
+                if let (Some(wgpu_star_ctx), Some(window)) = (self.wgpu_star_ctx.as_mut(), self.window.as_ref()) {
+                    let position = to_ndc(location, window.inner_size());
+                    let view = wgpu_star_ctx.view;
+                    match self.touch.update(id, phase, position) {
+                        Some(TouchGesture::Tap(at)) => wgpu_star_ctx.spawn_burst(view.to_scene(at)),
+                        Some(TouchGesture::Drag { from, to }) => {
+                            wgpu_star_ctx.drag_stars(view.to_scene(from), view.to_scene(to));
+                        }
+                        Some(TouchGesture::Pinch { center, factor }) => wgpu_star_ctx.view.zoom_about(center, factor),
+                        None => (),
+                    }
+                    // 触れている指もカーソルと同じ力を及ぼす
+                    let view = wgpu_star_ctx.view;
+                    wgpu_star_ctx.touch_points = self.touch.positions().map(|point| view.to_scene(point)).collect();
+                    self.pacer.mark_dirty();
+                }
+            }
             WindowEvent::RedrawRequested => {
                 /* if let Some(wgpu_ctx) = self.wgpu_ctx.as_mut() {
                     wgpu_ctx.draw();
                 } */
                if let Some(wgpu_star_ctx) = self.wgpu_star_ctx.as_mut() {
                    wgpu_star_ctx.draw();
+                   self.pacer.frame_presented();
                }
             }
             _ => (),
         }
     }
+
+    fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+        if let Some(window) = self.window.as_ref() {
+            self.pacer.about_to_wait(event_loop, window);
+        }
+    }
 }
 
 fn main() {
-    let event_loop = EventLoop::new().unwrap();
+    let config = AppConfig::from_args();
+    if config.benchmark {
+        println!("{}", benchmark::run(config.benchmark_frames));
+        return;
+    }
 
-    // ControlFlow::Poll continuously runs the event loop, even if the OS hasn't
-    // dispatched any events. This is ideal for games and similar applications.
-    event_loop.set_control_flow(ControlFlow::Poll);
+    let event_loop = EventLoop::new().unwrap();
 
-    let mut app = App::default();
+    // 描画タイミング（ControlFlow）は FramePacer が about_to_wait で毎回設定する
+    let mut app = App::new(config);
     let _ = event_loop.run_app(&mut app)
         .map_err(|err| {
             eprintln!("Event Loop Error: {:?}",err)
         });
 
-}
\ No newline at end of file
+}
@@ -0,0 +1,278 @@
+pub mod font;
+pub mod stats;
+
+use std::borrow::Cow;
+
+use bytemuck::{Pod, Zeroable};
+use wgpu::{util::DeviceExt, ShaderSource};
+
+use stats::FrameStats;
+
+/// 文字1つ分（または背景パネル）のインスタンスデータ
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+struct GlyphInstance {
+    position: [f32; 2],
+    size: [f32; 2],
+    color: [f32; 4],
+    bits: [u32; 2],
+}
+
+impl GlyphInstance {
+    const LAYOUT: wgpu::VertexBufferLayout<'static> = wgpu::VertexBufferLayout {
+        array_stride: std::mem::size_of::<Self>() as wgpu::BufferAddress,
+        attributes: &wgpu::vertex_attr_array![
+            0 => Float32x2,
+            1 => Float32x2,
+            2 => Float32x4,
+            3 => Uint32x2
+        ],
+        step_mode: wgpu::VertexStepMode::Instance,
+    };
+
+    fn new(position: [f32; 2], size: [f32; 2], color: [f32; 4], bits: u64) -> Self {
+        Self {
+            position,
+            size,
+            color,
+            bits: [bits as u32, (bits >> 32) as u32],
+        }
+    }
+}
+
+/// FPSなどの統計情報を星空の上に表示するオーバーレイ
+pub struct StatsOverlay {
+    pub visible: bool,
+    pipeline: wgpu::RenderPipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    format: wgpu::TextureFormat,
+    screen_buffer: wgpu::Buffer,
+    screen_bind_group: wgpu::BindGroup,
+    instance_buffer: wgpu::Buffer,
+    instance_count: u32,
+}
+
+impl StatsOverlay {
+    /// 一度に描画できる文字数（背景パネルを含む）
+    const MAX_GLYPHS: u64 = 512;
+    /// 1ドットの大きさ（ピクセル）
+    const DOT: f32 = 2.0;
+    const MARGIN: f32 = 8.0;
+    const TEXT_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+    const PANEL_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.6];
+
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+        depth_stencil: Option<wgpu::DepthStencilState>,
+    ) -> Self {
+        let bind_group_layout = device.create_bind_group_layout(
+            &wgpu::BindGroupLayoutDescriptor {
+                label: Some("Overlay Bind Group Layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::VERTEX,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None
+                        },
+                        count: None
+                    }
+                ]
+            }
+        );
+
+        let pipeline = Self::create_pipeline(device, &bind_group_layout, format, sample_count, depth_stencil);
+
+        let screen_buffer = device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("Overlay Screen Buffer"),
+                contents: bytemuck::cast_slice(&[0.0f32; 4]),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST
+            }
+        );
+
+        let screen_bind_group = device.create_bind_group(
+            &wgpu::BindGroupDescriptor {
+                label: Some("Overlay Bind Group"),
+                layout: &bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: screen_buffer.as_entire_binding(),
+                    }
+                ],
+            }
+        );
+
+        let instance_buffer = device.create_buffer(
+            &wgpu::BufferDescriptor {
+                label: Some("Overlay Instance Buffer"),
+                size: Self::MAX_GLYPHS * std::mem::size_of::<GlyphInstance>() as u64,
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false
+            }
+        );
+
+        Self {
+            visible: false,
+            pipeline,
+            bind_group_layout,
+            format,
+            screen_buffer,
+            screen_bind_group,
+            instance_buffer,
+            instance_count: 0,
+        }
+    }
+
+    fn create_pipeline(
+        device: &wgpu::Device,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+        depth_stencil: Option<wgpu::DepthStencilState>,
+    ) -> wgpu::RenderPipeline {
+        let pipeline_layout = device.create_pipeline_layout(
+            &wgpu::PipelineLayoutDescriptor {
+                label: Some("Overlay Pipeline Layout"),
+                bind_group_layouts: &[bind_group_layout],
+                push_constant_ranges: &[]
+            }
+        );
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Overlay Shader"),
+            source: ShaderSource::Wgsl(Cow::Borrowed(include_str!("text_shader.wgsl"))),
+        });
+
+        device.create_render_pipeline(
+            &wgpu::RenderPipelineDescriptor {
+                label: Some("Overlay Pipeline"),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: Some("vertexMain"),
+                    compilation_options: Default::default(),
+                    buffers: &[GlyphInstance::LAYOUT],
+                },
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleStrip,
+                    ..Default::default()
+                },
+                depth_stencil,
+                multisample: wgpu::MultisampleState {
+                    count: sample_count,
+                    ..Default::default()
+                },
+                multiview: None,
+                cache: None,
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: Some("fragmentMain"),
+                    targets: &[Some(wgpu::ColorTargetState {
+                        write_mask: wgpu::ColorWrites::ALL,
+                        format,
+                        blend: Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+                    })],
+                    compilation_options: Default::default(),
+                })
+            }
+        )
+    }
+
+    /// 描画先のMSAAのサンプル数と深度の設定に合わせてパイプラインを作り直す
+    pub fn set_target(
+        &mut self,
+        device: &wgpu::Device,
+        sample_count: u32,
+        depth_stencil: Option<wgpu::DepthStencilState>,
+    ) {
+        self.pipeline = Self::create_pipeline(device, &self.bind_group_layout, self.format, sample_count, depth_stencil);
+    }
+
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    /// 表示する文字列を組み立てる
+    pub fn lines(stats: &FrameStats, star_count: u32, adapter: &wgpu::AdapterInfo) -> Vec<String> {
+        let millis = |time: Option<std::time::Duration>| match time {
+            Some(time) => format!("{:.2} MS", time.as_secs_f64() * 1000.0),
+            None => "N/A".to_string(),
+        };
+        let mut lines = vec![
+            match stats.fps() {
+                Some(fps) => format!("FPS: {:.1}", fps),
+                None => "FPS: N/A".to_string(),
+            },
+            format!("CPU: {}", millis(stats.cpu_time())),
+            format!("GPU: {}", millis(stats.gpu_time())),
+        ];
+        lines.extend(
+            stats
+                .gpu_pass_times()
+                .map(|(label, time)| format!("  {}: {}", label, millis(Some(time))))
+        );
+        lines.push(format!("STARS: {}", star_count));
+        lines.push(format!("ADAPTER: {} ({:?})", adapter.name, adapter.backend));
+        lines
+    }
+
+    /// 文字列をインスタンスデータに変換してバッファに書き込む
+    pub fn prepare(&mut self, queue: &wgpu::Queue, screen_size: (u32, u32), lines: &[String]) {
+        let (width, height) = screen_size;
+        queue.write_buffer(
+            &self.screen_buffer,
+            0,
+            bytemuck::cast_slice(&[2.0 / width.max(1) as f32, 2.0 / height.max(1) as f32, 0.0, 0.0])
+        );
+
+        let glyph_size = [
+            font::GLYPH_WIDTH as f32 * Self::DOT,
+            font::GLYPH_HEIGHT as f32 * Self::DOT,
+        ];
+        let advance = [glyph_size[0] + Self::DOT, glyph_size[1] + Self::DOT * 2.0];
+        let columns = lines.iter().map(|line| line.chars().count()).max().unwrap_or(0);
+
+        let mut glyphs = vec![GlyphInstance::new(
+            [Self::MARGIN, Self::MARGIN],
+            [
+                columns as f32 * advance[0] + Self::DOT * 4.0,
+                lines.len() as f32 * advance[1] + Self::DOT * 2.0,
+            ],
+            Self::PANEL_COLOR,
+            font::FILLED,
+        )];
+        for (row, line) in lines.iter().enumerate() {
+            for (col, c) in line.chars().enumerate().filter(|(_, c)| *c != ' ') {
+                glyphs.push(GlyphInstance::new(
+                    [
+                        Self::MARGIN + Self::DOT * 2.0 + col as f32 * advance[0],
+                        Self::MARGIN + Self::DOT * 2.0 + row as f32 * advance[1],
+                    ],
+                    glyph_size,
+                    Self::TEXT_COLOR,
+                    font::glyph_bits(c),
+                ));
+            }
+        }
+        glyphs.truncate(Self::MAX_GLYPHS as usize);
+
+        queue.write_buffer(&self.instance_buffer, 0, bytemuck::cast_slice(&glyphs));
+        self.instance_count = glyphs.len() as u32;
+    }
+
+    pub fn draw(&self, rpass: &mut wgpu::RenderPass<'_>) {
+        if !self.visible || self.instance_count == 0 {
+            return;
+        }
+        rpass.set_pipeline(&self.pipeline);
+        rpass.set_bind_group(0, &self.screen_bind_group, &[]);
+        rpass.set_vertex_buffer(0, self.instance_buffer.slice(..));
+        rpass.draw(0..4, 0..self.instance_count);
+    }
+}
@@ -0,0 +1,105 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// 直近のフレームの計測値を保持し、移動平均を求める
+#[derive(Debug)]
+pub struct FrameStats {
+    last_frame: Option<Instant>,
+    frame_intervals: VecDeque<Duration>,
+    cpu_times: VecDeque<Duration>,
+    gpu_times: VecDeque<Duration>,
+    /// まだ取り出されていない最新のGPU時間
+    latest_gpu_time: Option<Duration>,
+    /// レンダーパスごとのGPU時間
+    gpu_passes: Vec<(&'static str, VecDeque<Duration>)>,
+}
+
+impl Default for FrameStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FrameStats {
+    /// 移動平均に使うフレーム数
+    const WINDOW: usize = 60;
+
+    pub fn new() -> Self {
+        Self {
+            last_frame: None,
+            frame_intervals: VecDeque::with_capacity(Self::WINDOW),
+            cpu_times: VecDeque::with_capacity(Self::WINDOW),
+            gpu_times: VecDeque::with_capacity(Self::WINDOW),
+            latest_gpu_time: None,
+            gpu_passes: Vec::new(),
+        }
+    }
+
+    fn push(samples: &mut VecDeque<Duration>, sample: Duration) {
+        if samples.len() == Self::WINDOW {
+            samples.pop_front();
+        }
+        samples.push_back(sample);
+    }
+
+    fn average(samples: &VecDeque<Duration>) -> Option<Duration> {
+        if samples.is_empty() {
+            return None;
+        }
+        Some(samples.iter().sum::<Duration>() / samples.len() as u32)
+    }
+
+    /// フレームの開始時に呼び、前のフレームとの間隔を記録する
+    pub fn begin_frame(&mut self) -> Instant {
+        let now = Instant::now();
+        if let Some(last) = self.last_frame.replace(now) {
+            Self::push(&mut self.frame_intervals, now - last);
+        }
+        now
+    }
+
+    /// フレームの記録にかかったCPU時間を記録する
+    pub fn end_frame(&mut self, frame_start: Instant) {
+        Self::push(&mut self.cpu_times, frame_start.elapsed());
+    }
+
+    /// GPUでの描画時間を記録する
+    pub fn record_gpu_time(&mut self, gpu_time: Duration) {
+        Self::push(&mut self.gpu_times, gpu_time);
+        self.latest_gpu_time = Some(gpu_time);
+    }
+
+    /// 前回取り出した後に記録されたGPU時間を取り出す
+    pub fn take_latest_gpu_time(&mut self) -> Option<Duration> {
+        self.latest_gpu_time.take()
+    }
+
+    /// レンダーパスごとのGPU時間を記録する
+    pub fn record_gpu_pass(&mut self, label: &'static str, gpu_time: Duration) {
+        match self.gpu_passes.iter_mut().find(|(pass, _)| *pass == label) {
+            Some((_, samples)) => Self::push(samples, gpu_time),
+            None => self.gpu_passes.push((label, VecDeque::from([gpu_time]))),
+        }
+    }
+
+    pub fn fps(&self) -> Option<f32> {
+        Self::average(&self.frame_intervals)
+            .filter(|interval| !interval.is_zero())
+            .map(|interval| 1.0 / interval.as_secs_f32())
+    }
+
+    pub fn cpu_time(&self) -> Option<Duration> {
+        Self::average(&self.cpu_times)
+    }
+
+    pub fn gpu_time(&self) -> Option<Duration> {
+        Self::average(&self.gpu_times)
+    }
+
+    /// レンダーパスごとのGPU時間の移動平均
+    pub fn gpu_pass_times(&self) -> impl Iterator<Item = (&'static str, Duration)> + '_ {
+        self.gpu_passes
+            .iter()
+            .filter_map(|(label, samples)| Self::average(samples).map(|time| (*label, time)))
+    }
+}
@@ -0,0 +1,57 @@
+struct Screen {
+    // 1ピクセルあたりのクリップ座標での大きさ
+    pixelSize: vec2<f32>,
+}
+
+struct GlyphInstance {
+    // 左上の位置（ピクセル）
+    @location(0) position: vec2<f32>,
+    // 大きさ（ピクセル）
+    @location(1) size: vec2<f32>,
+    @location(2) color: vec4<f32>,
+    // 行 * 5 + 列 番目のビットが点灯するドット
+    @location(3) bits: vec2<u32>,
+}
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) color: vec4<f32>,
+    @location(2) @interpolate(flat) bits: vec2<u32>,
+}
+
+@binding(0) @group(0) var<uniform> screen: Screen;
+
+@vertex
+fn vertexMain(
+    @builtin(vertex_index) vertexIdx: u32,
+    glyph: GlyphInstance,
+) -> VertexOutput {
+    // 4頂点のトライアングルストリップで四角形を作る
+    let corner = vec2<f32>(f32(vertexIdx & 1u), f32(vertexIdx >> 1u));
+    let pixel = glyph.position + corner * glyph.size;
+
+    var out: VertexOutput;
+    out.position = vec4<f32>(
+        -1.0 + pixel.x * screen.pixelSize.x,
+        1.0 - pixel.y * screen.pixelSize.y,
+        0.0,
+        1.0
+    );
+    out.uv = corner;
+    out.color = glyph.color;
+    out.bits = glyph.bits;
+    return out;
+}
+
+@fragment
+fn fragmentMain(in: VertexOutput) -> @location(0) vec4<f32> {
+    let col = min(u32(in.uv.x * 5.0), 4u);
+    let row = min(u32(in.uv.y * 7.0), 6u);
+    let idx = row * 5u + col;
+    let word = select(in.bits.x, in.bits.y, idx >= 32u);
+    if ((word >> (idx % 32u)) & 1u) == 0u {
+        discard;
+    }
+    return vec4<f32>(in.color.rgb * in.color.a, in.color.a);
+}
@@ -0,0 +1,150 @@
+struct Uniforms {
+    time: f32,
+    // 移動と回転に使う時間（CPUで動かす時は0）
+    motionTime: f32,
+    // 星空を拡大率 viewZoom で拡大してから viewOffset だけずらす
+    viewOffset: vec2<f32>,
+    viewZoom: f32,
+}
+
+struct SdfStyle {
+    fillColor: vec4<f32>,
+    outlineColor: vec4<f32>,
+    // 星の外接円の半径を1とした時の太さ・広がり
+    outlineWidth: f32,
+    glowRadius: f32,
+    glowStrength: f32,
+}
+
+struct InstanceInput {
+    @location(2) position: vec2<f32>,
+    @location(3) scale: f32,
+    @location(4) initialRotation: f32,
+    @location(5) speed: vec2<f32>,
+    @location(6) rotationSpeed: f32,
+    @location(7) opacity: f32,
+    @location(8) twinklePhase: f32,
+    @location(9) twinkleFrequency: f32,
+    // 奥行き（0が手前、1が奥）
+    @location(11) depth: f32,
+    @location(15) color: vec3<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    // 星の中心を原点、外接円の半径を1とした座標
+    @location(0) local: vec2<f32>,
+    @location(1) alpha: f32,
+    @location(2) color: vec3<f32>,
+}
+
+@binding(0) @group(0) var<uniform> uniforms: Uniforms;
+
+const TAU: f32 = 6.28318531;
+// またたきで暗くなる最大の割合
+const TWINKLE_DEPTH: f32 = 0.6;
+
+// -1.0..1.0 の範囲でラップする（CPUの simulation::wrap と同じ）
+fn wrap(value: f32) -> f32 {
+    return (value + 1.0) - 2.0 * floor((value + 1.0) / 2.0) - 1.0;
+}
+
+// またたきを含めた不透明度
+fn starAlpha(instance: InstanceInput) -> f32 {
+    let wave = 0.5 + 0.5 * sin(uniforms.time * instance.twinkleFrequency * TAU + instance.twinklePhase);
+    let twinkle = select(1.0, 1.0 - TWINKLE_DEPTH * wave, instance.twinkleFrequency > 0.0);
+    return clamp(instance.opacity * twinkle, 0.0, 1.0);
+}
+@binding(1) @group(0) var<uniform> style: SdfStyle;
+
+const PI: f32 = 3.14159265;
+const POINTS: u32 = 5u;
+const INNER_RADIUS: f32 = 0.38;
+
+@vertex
+fn vertexMain(
+    @builtin(vertex_index) vertexIdx: u32,
+    instance: InstanceInput,
+) -> VertexOutput {
+    // 光彩や輪郭線が収まるように四角形を広げる
+    let glowExtent = select(0.0, style.glowRadius * 4.0, style.glowStrength > 0.0);
+    let extent = 1.0 + max(glowExtent, style.outlineWidth);
+    let corner = vec2<f32>(f32(vertexIdx & 1u), f32(vertexIdx >> 1u)) * 2.0 - 1.0;
+    let local = corner * extent;
+
+    // メッシュでの描画と同じ回転・移動・ラップ
+    let rotation = instance.initialRotation + uniforms.motionTime * instance.rotationSpeed;
+    let moveX = instance.position.x + instance.speed.x * uniforms.motionTime;
+    let moveY = instance.position.y + instance.speed.y * uniforms.motionTime;
+
+    let c = cos(rotation);
+    let s = sin(rotation);
+    let rotMatrix = mat2x2<f32>(
+        c, -s,
+        s, c
+    );
+    let rotatedPos = rotMatrix * (local * instance.scale);
+
+    let wrappedX = wrap(moveX);
+    let wrappedY = wrap(moveY);
+
+    var out: VertexOutput;
+    let finalPos = rotatedPos + vec2<f32>(wrappedX, wrappedY);
+    out.position = vec4<f32>(finalPos * uniforms.viewZoom + uniforms.viewOffset, clamp(instance.depth, 0.0, 1.0), 1.0);
+    out.local = local;
+    out.alpha = starAlpha(instance);
+    out.color = instance.color;
+    return out;
+}
+
+// PositionVertex::new_vecs と同じ外周の頂点
+fn starVertex(i: u32) -> vec2<f32> {
+    let radius = select(INNER_RADIUS, 1.0, i % 2u == 0u);
+    let angle = f32(i) * PI / f32(POINTS);
+    return vec2<f32>(cos(angle), sin(angle)) * radius;
+}
+
+// 星型の多角形までの符号付き距離（内側が負）
+fn sdStar(p: vec2<f32>) -> f32 {
+    let v0 = starVertex(0u);
+    var d = dot(p - v0, p - v0);
+    var s = 1.0;
+    for (var i = 0u; i < POINTS * 2u; i++) {
+        let vi = starVertex(i);
+        let vj = starVertex((i + POINTS * 2u - 1u) % (POINTS * 2u));
+        let e = vj - vi;
+        let w = p - vi;
+        let b = w - e * clamp(dot(w, e) / dot(e, e), 0.0, 1.0);
+        d = min(d, dot(b, b));
+        let c = vec3<bool>(p.y >= vi.y, p.y < vj.y, e.x * w.y > e.y * w.x);
+        if all(c) || all(!c) {
+            s = -s;
+        }
+    }
+    return s * sqrt(d);
+}
+
+@fragment
+fn fragmentMain(in: VertexOutput) -> @location(0) vec4<f32> {
+    let d = sdStar(in.local);
+    let aa = max(fwidth(d), 1e-4);
+
+    // 出力は乗算済みアルファ
+    let fillColor = style.fillColor.rgb * in.color;
+    let fill = 1.0 - smoothstep(-aa, aa, d);
+    let glow = style.glowStrength * exp(-max(d, 0.0) / max(style.glowRadius, 1e-4)) * (1.0 - fill);
+    var color = vec4<f32>(fillColor * glow, glow);
+    color = vec4<f32>(fillColor, 1.0) * style.fillColor.a * fill + color * (1.0 - fill * style.fillColor.a);
+
+    if style.outlineWidth > 0.0 {
+        let outline = 1.0 - smoothstep(style.outlineWidth * 0.5 - aa, style.outlineWidth * 0.5 + aa, abs(d));
+        let outlineAlpha = outline * style.outlineColor.a;
+        color = vec4<f32>(style.outlineColor.rgb * outlineAlpha, outlineAlpha) + color * (1.0 - outlineAlpha);
+    }
+    let out = color * in.alpha;
+    // 深度バッファを使う時、見えない四角形の隅で後ろの星を隠さないようにする
+    if out.a <= 0.0 {
+        discard;
+    }
+    return out;
+}
@@ -0,0 +1,105 @@
+use crate::vertex::instance::StarInstance;
+
+/// 星の動きをどこで計算するか
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum SimulationMode {
+    /// 経過時間からシェーダーで位置と回転を求める
+    #[default]
+    Gpu,
+    /// CPUで毎フレーム位置と回転を進め、インスタンスバッファを書き換える
+    Cpu,
+}
+
+impl SimulationMode {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "gpu" => Some(SimulationMode::Gpu),
+            "cpu" => Some(SimulationMode::Cpu),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SimulationMode::Gpu => "gpu",
+            SimulationMode::Cpu => "cpu",
+        }
+    }
+}
+
+/// 描画先の端に来た星をどうするか
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum EdgeMode {
+    /// 反対側の端から出てくる
+    #[default]
+    Wrap,
+    /// 端で跳ね返る（CPUで動かす時だけ）
+    Bounce,
+    /// 端から出たら寿命が尽きたことにする（エミッターと花火の枠で使う。CPUで動かす時だけ）
+    Vanish,
+}
+
+impl EdgeMode {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "wrap" => Some(EdgeMode::Wrap),
+            "bounce" => Some(EdgeMode::Bounce),
+            "vanish" => Some(EdgeMode::Vanish),
+            _ => None,
+        }
+    }
+}
+
+/// 力を受けた星の速さの上限（単位/秒）
+const MAX_SPEED: f32 = 2.0;
+
+/// CPUで1コマに進める時間の上限（秒）
+/// ウィンドウを動かしている間などに止まっていても、再開した時に星が飛んだり力が発散したりしない
+pub const MAX_STEP: f32 = 0.1;
+
+/// 力で加速した星が速くなりすぎないように抑える
+pub(crate) fn limit_speed(star: &mut StarInstance) {
+    let speed = (star.speed[0] * star.speed[0] + star.speed[1] * star.speed[1]).sqrt();
+    if speed > MAX_SPEED {
+        star.speed = star.speed.map(|s| s * MAX_SPEED / speed);
+    }
+}
+
+/// -1.0..1.0 の範囲でラップする
+fn wrap(value: f32) -> f32 {
+    (value + 1.0).rem_euclid(2.0) - 1.0
+}
+
+/// 半径 `radius` の星が -1.0..1.0 からはみ出さないように押し戻し、速度を内向きにする
+fn bounce(position: &mut f32, speed: &mut f32, radius: f32) {
+    let limit = (1.0 - radius).max(0.0);
+    if *position < -limit {
+        *position = -limit;
+        *speed = speed.abs();
+    } else if *position > limit {
+        *position = limit;
+        *speed = -speed.abs();
+    }
+}
+
+/// 星を dt 秒だけ進める
+pub fn step_cpu(instances: &mut [StarInstance], dt: f32, edges: EdgeMode) {
+    for star in instances.iter_mut() {
+        for axis in 0..2 {
+            let position = star.position[axis] + star.speed[axis] * dt;
+            match edges {
+                EdgeMode::Wrap => star.position[axis] = wrap(position),
+                EdgeMode::Bounce => {
+                    star.position[axis] = position;
+                    bounce(&mut star.position[axis], &mut star.speed[axis], star.scale);
+                }
+                // 端から出た星は `EmitterSystem::advance` が寿命を終わらせる
+                EdgeMode::Vanish => star.position[axis] = position,
+            }
+        }
+        star.initial_rotation = (star.initial_rotation + star.rotation_speed * dt)
+            .rem_euclid(std::f32::consts::TAU);
+        // 3Dで描く時は奥から手前へ進み、手前まで来たら奥に戻る
+        star.z = (star.z - star.z_speed * dt).rem_euclid(1.0);
+    }
+}
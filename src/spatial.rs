@@ -0,0 +1,118 @@
+/// 描画先の範囲（-1.0..1.0）を同じ大きさのマス目に分け、近くの星を素早く探すための索引
+/// 範囲の外にある点は端のマスに入れる
+#[derive(Clone, Debug, Default)]
+pub struct SpatialGrid {
+    /// 1辺のマスの数
+    cells_per_side: usize,
+    /// マスごとの `indices` の開始位置（マスの数 + 1 個）
+    cell_starts: Vec<u32>,
+    /// マスの順に並べた星の番号
+    indices: Vec<u32>,
+    /// 星ごとのマスの番号
+    cell_of: Vec<u32>,
+}
+
+impl SpatialGrid {
+    /// 1辺のマスの数の上限（星が小さくてもマス目が大きくなりすぎないようにする）
+    const MAX_CELLS_PER_SIDE: usize = 256;
+
+    fn cell_coord(&self, value: f32) -> usize {
+        let cell = ((value + 1.0) * 0.5 * self.cells_per_side as f32).floor();
+        (cell.max(0.0) as usize).min(self.cells_per_side - 1)
+    }
+
+    /// マスの1辺が `cell_size` 以上になるように分けて、`positions` を入れ直す
+    pub fn rebuild(&mut self, positions: impl ExactSizeIterator<Item = [f32; 2]>, cell_size: f32) {
+        self.cells_per_side = ((2.0 / cell_size.max(1e-4)).floor() as usize).clamp(1, Self::MAX_CELLS_PER_SIDE);
+        let cell_count = self.cells_per_side * self.cells_per_side;
+
+        self.cell_of.clear();
+        self.cell_of.reserve(positions.len());
+        for [x, y] in positions {
+            self.cell_of.push((self.cell_coord(y) * self.cells_per_side + self.cell_coord(x)) as u32);
+        }
+
+        // マスごとの数を数えてから、その累積和の位置に番号を並べる
+        self.cell_starts.clear();
+        self.cell_starts.resize(cell_count + 1, 0);
+        for &cell in &self.cell_of {
+            self.cell_starts[cell as usize + 1] += 1;
+        }
+        for cell in 0..cell_count {
+            self.cell_starts[cell + 1] += self.cell_starts[cell];
+        }
+        let mut next = self.cell_starts.clone();
+        self.indices.clear();
+        self.indices.resize(self.cell_of.len(), 0);
+        for (index, &cell) in self.cell_of.iter().enumerate() {
+            self.indices[next[cell as usize] as usize] = index as u32;
+            next[cell as usize] += 1;
+        }
+    }
+
+    /// `at` から `radius` 以内にあるかもしれない星の番号を渡す
+    /// 距離は確かめないので、呼び出し側で確かめる
+    pub fn for_each_near(&self, at: [f32; 2], radius: f32, mut f: impl FnMut(usize)) {
+        if self.cells_per_side == 0 {
+            return;
+        }
+        let (x0, x1) = (self.cell_coord(at[0] - radius), self.cell_coord(at[0] + radius));
+        let (y0, y1) = (self.cell_coord(at[1] - radius), self.cell_coord(at[1] + radius));
+        for y in y0..=y1 {
+            let row = y * self.cells_per_side;
+            let (start, end) = (self.cell_starts[row + x0] as usize, self.cell_starts[row + x1 + 1] as usize);
+            // 同じ行の隣り合うマスは `indices` の中で続いている
+            self.indices[start..end].iter().for_each(|&index| f(index as usize));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn near(grid: &SpatialGrid, at: [f32; 2], radius: f32) -> Vec<usize> {
+        let mut found = Vec::new();
+        grid.for_each_near(at, radius, |index| found.push(index));
+        found.sort();
+        found
+    }
+
+    #[test]
+    fn empty_grid_finds_nothing() {
+        assert!(near(&SpatialGrid::default(), [0.0, 0.0], 1.0).is_empty());
+    }
+
+    #[test]
+    fn finds_neighbours_across_cell_boundaries() {
+        let positions = [[0.09, 0.09], [0.11, 0.11], [0.11, 0.09], [0.5, 0.5], [-0.6, 0.1]];
+        let mut grid = SpatialGrid::default();
+        grid.rebuild(positions.into_iter(), 0.1);
+        // 0.1 の境目をまたいだ隣のマスの星も見つかり、遠くの星は渡さない
+        assert_eq!(near(&grid, [0.1, 0.1], 0.05), vec![0, 1, 2]);
+        assert_eq!(near(&grid, [0.5, 0.5], 0.01), vec![3]);
+        assert!(near(&grid, [0.0, -0.5], 0.05).is_empty());
+    }
+
+    #[test]
+    fn clamps_points_outside_the_range_into_edge_cells() {
+        let positions = [[-1.5, 0.0], [3.0, 3.0], [1.0, -1.0]];
+        let mut grid = SpatialGrid::default();
+        grid.rebuild(positions.into_iter(), 0.5);
+        assert_eq!(near(&grid, [-0.9, 0.0], 0.05), vec![0]);
+        assert_eq!(near(&grid, [0.9, 0.9], 0.05), vec![1]);
+        assert_eq!(near(&grid, [0.9, -0.9], 0.05), vec![2]);
+        // 範囲の外を探しても端のマスを見る
+        assert_eq!(near(&grid, [5.0, 5.0], 0.1), vec![1]);
+    }
+
+    #[test]
+    fn limits_the_number_of_cells() {
+        let mut grid = SpatialGrid::default();
+        grid.rebuild([[0.0, 0.0]].into_iter(), 0.0);
+        assert_eq!(grid.cells_per_side, SpatialGrid::MAX_CELLS_PER_SIDE);
+        grid.rebuild([[0.0, 0.0]].into_iter(), 10.0);
+        assert_eq!(grid.cells_per_side, 1);
+        assert_eq!(near(&grid, [0.9, 0.9], 0.0), vec![0]);
+    }
+}
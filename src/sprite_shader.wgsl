@@ -0,0 +1,108 @@
+struct Uniforms {
+    time: f32,
+    // 移動と回転に使う時間（CPUで動かす時は0）
+    motionTime: f32,
+    // 星空を拡大率 viewZoom で拡大してから viewOffset だけずらす
+    viewOffset: vec2<f32>,
+    viewZoom: f32,
+}
+
+struct Atlas {
+    // アトラスの列数と行数
+    grid: vec2<u32>,
+    // アトラスの画素数
+    size: vec2<u32>,
+}
+
+struct InstanceInput {
+    @location(2) position: vec2<f32>,
+    @location(3) scale: f32,
+    @location(4) initialRotation: f32,
+    @location(5) speed: vec2<f32>,
+    @location(6) rotationSpeed: f32,
+    @location(7) opacity: f32,
+    @location(8) twinklePhase: f32,
+    @location(9) twinkleFrequency: f32,
+    @location(10) sprite: u32,
+    // 奥行き（0が手前、1が奥）
+    @location(11) depth: f32,
+}
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) alpha: f32,
+}
+
+@binding(0) @group(0) var<uniform> uniforms: Uniforms;
+@binding(0) @group(1) var<uniform> atlas: Atlas;
+@binding(1) @group(1) var atlasTexture: texture_2d<f32>;
+@binding(2) @group(1) var atlasSampler: sampler;
+
+const TAU: f32 = 6.28318531;
+// またたきで暗くなる最大の割合
+const TWINKLE_DEPTH: f32 = 0.6;
+
+// -1.0..1.0 の範囲でラップする（CPUの simulation::wrap と同じ）
+fn wrap(value: f32) -> f32 {
+    return (value + 1.0) - 2.0 * floor((value + 1.0) / 2.0) - 1.0;
+}
+
+// またたきを含めた不透明度
+fn starAlpha(instance: InstanceInput) -> f32 {
+    let wave = 0.5 + 0.5 * sin(uniforms.time * instance.twinkleFrequency * TAU + instance.twinklePhase);
+    let twinkle = select(1.0, 1.0 - TWINKLE_DEPTH * wave, instance.twinkleFrequency > 0.0);
+    return clamp(instance.opacity * twinkle, 0.0, 1.0);
+}
+
+@vertex
+fn vertexMain(
+    @builtin(vertex_index) vertexIdx: u32,
+    instance: InstanceInput,
+) -> VertexOutput {
+    // 4頂点のトライアングルストリップで、星の外接円を囲む四角形を作る
+    let corner = vec2<f32>(f32(vertexIdx & 1u), f32(vertexIdx >> 1u));
+    let local = corner * 2.0 - 1.0;
+
+    // メッシュでの描画と同じ回転・移動・ラップ
+    let rotation = instance.initialRotation + uniforms.motionTime * instance.rotationSpeed;
+    let moveX = instance.position.x + instance.speed.x * uniforms.motionTime;
+    let moveY = instance.position.y + instance.speed.y * uniforms.motionTime;
+
+    let c = cos(rotation);
+    let s = sin(rotation);
+    let rotMatrix = mat2x2<f32>(
+        c, -s,
+        s, c
+    );
+    let rotatedPos = rotMatrix * (local * instance.scale);
+
+    let wrappedX = wrap(moveX);
+    let wrappedY = wrap(moveY);
+
+    // アトラスの中から sprite 番目のセルを選ぶ（左上から行ごとに数える）
+    let sprite = instance.sprite % (atlas.grid.x * atlas.grid.y);
+    let cell = vec2<f32>(f32(sprite % atlas.grid.x), f32(sprite / atlas.grid.x));
+    // 線形補間で隣のセルの色が混ざらないように、セルの端から半画素だけ内側を使う
+    let inset = 0.5 * vec2<f32>(atlas.grid) / vec2<f32>(atlas.size);
+    let uv = mix(inset, 1.0 - inset, vec2<f32>(corner.x, 1.0 - corner.y));
+
+    var out: VertexOutput;
+    let finalPos = rotatedPos + vec2<f32>(wrappedX, wrappedY);
+    out.position = vec4<f32>(finalPos * uniforms.viewZoom + uniforms.viewOffset, clamp(instance.depth, 0.0, 1.0), 1.0);
+    out.uv = (cell + uv) / vec2<f32>(atlas.grid);
+    out.alpha = starAlpha(instance);
+    return out;
+}
+
+@fragment
+fn fragmentMain(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(atlasTexture, atlasSampler, in.uv);
+    // 出力は乗算済みアルファ
+    let out = vec4<f32>(color.rgb * color.a, color.a) * in.alpha;
+    // 深度バッファを使う時、透明なところで後ろの星を隠さないようにする
+    if out.a <= 0.0 {
+        discard;
+    }
+    return out;
+}
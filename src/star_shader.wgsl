@@ -1,5 +1,10 @@
 struct Uniforms {
     time: f32,
+    // 移動と回転に使う時間（CPUで動かす時は0）
+    motionTime: f32,
+    // 星空を拡大率 viewZoom で拡大してから viewOffset だけずらす
+    viewOffset: vec2<f32>,
+    viewZoom: f32,
 }
 
 struct InstanceInput {
@@ -8,20 +13,48 @@ struct InstanceInput {
     @location(4) initialRotation: f32,
     @location(5) speed: vec2<f32>,
     @location(6) rotationSpeed: f32,
+    @location(7) opacity: f32,
+    @location(8) twinklePhase: f32,
+    @location(9) twinkleFrequency: f32,
+    // 奥行き（0が手前、1が奥）
+    @location(11) depth: f32,
+    @location(15) color: vec3<f32>,
 }
 
 @binding(0) @group(0) var<uniform> uniforms: Uniforms;
 
+const TAU: f32 = 6.28318531;
+// またたきで暗くなる最大の割合
+const TWINKLE_DEPTH: f32 = 0.6;
+
+// -1.0..1.0 の範囲でラップする（CPUの simulation::wrap と同じ）
+fn wrap(value: f32) -> f32 {
+    return (value + 1.0) - 2.0 * floor((value + 1.0) / 2.0) - 1.0;
+}
+
+// またたきを含めた不透明度
+fn starAlpha(instance: InstanceInput) -> f32 {
+    let wave = 0.5 + 0.5 * sin(uniforms.time * instance.twinkleFrequency * TAU + instance.twinklePhase);
+    let twinkle = select(1.0, 1.0 - TWINKLE_DEPTH * wave, instance.twinkleFrequency > 0.0);
+    return clamp(instance.opacity * twinkle, 0.0, 1.0);
+}
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) alpha: f32,
+    @location(1) color: vec3<f32>,
+}
+
 @vertex
 fn vertexMain(
     @location(0) position: vec2<f32>,
     @builtin(instance_index) instanceIdx: u32,
     instance: InstanceInput,
-) -> @builtin(position) vec4<f32> {
+) -> VertexOutput {
     // 時間に基づいて回転と移動を計算
-    let rotation = instance.initialRotation + uniforms.time * instance.rotationSpeed;
-    let moveX = instance.position.x + instance.speed.x * uniforms.time;
-    let moveY = instance.position.y + instance.speed.y * uniforms.time;
+    let rotation = instance.initialRotation + uniforms.motionTime * instance.rotationSpeed;
+    let moveX = instance.position.x + instance.speed.x * uniforms.motionTime;
+    let moveY = instance.position.y + instance.speed.y * uniforms.motionTime;
 
     // 回転行列
     let c = cos(rotation);
@@ -36,20 +69,23 @@ fn vertexMain(
     let rotatedPos = rotMatrix * scaledPos;
     
     // 最終位置の計算（画面内でラップする）
-    var wrappedX = select(moveX, moveX + 2.0, moveX < -1.0);
-    wrappedX = select(wrappedX, wrappedX - 2.0, wrappedX > 1.0);
-    var wrappedY = select(moveY, moveY + 2.0, moveY < -1.0);
-    wrappedY = select(wrappedY, wrappedY - 2.0, wrappedY > 1.0);
+    let wrappedX = wrap(moveX);
+    let wrappedY = wrap(moveY);
 
     let finalPos = vec2<f32>(
         rotatedPos.x + wrappedX,
         rotatedPos.y + wrappedY
     );
 
-    return vec4<f32>(finalPos, 0.0, 1.0);
+    var out: VertexOutput;
+    out.position = vec4<f32>(finalPos * uniforms.viewZoom + uniforms.viewOffset, clamp(instance.depth, 0.0, 1.0), 1.0);
+    out.color = instance.color;
+    out.alpha = starAlpha(instance);
+    return out;
 }
 
 @fragment
-fn fragmentMain() -> @location(0) vec4<f32> {
-    return vec4<f32>(1.0, 1.0, 0.0, 1.0); // 黄色で塗りつぶし
-}
\ No newline at end of file
+fn fragmentMain(in: VertexOutput) -> @location(0) vec4<f32> {
+    // 星の色で塗りつぶし（乗算済みアルファ）
+    return vec4<f32>(in.color, 1.0) * in.alpha;
+}
@@ -1,4 +1,4 @@
-use std::time::{Duration, SystemTime};
+use std::time::Instant;
 
 use bytemuck::{Pod, Zeroable};
 
@@ -6,24 +6,44 @@ use bytemuck::{Pod, Zeroable};
 #[derive(Clone, Copy,Debug,Pod,Zeroable)]
 pub struct TimeUniform {
     pub time: f32,
+    /// 星の移動と回転に使う時間（CPUで動かす時は0）
+    pub motion_time: f32,
+    /// 星空を拡大した後にずらす量（描画先の座標）
+    pub view_offset: [f32;2],
+    /// 星空の拡大率
+    pub view_zoom: f32,
     _padding: [u8;12]
 }
 
+impl Default for TimeUniform {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl TimeUniform {
     pub fn new() -> Self {
         Self {
             time: 0.0,
+            motion_time: 0.0,
+            view_offset: [0.0, 0.0],
+            view_zoom: 1.0,
             _padding: [0;12]
         }
     }
 
-    pub fn after_duration(&self) -> Self {
+    /// 開始時刻からの経過秒数を持つユニフォーム
+    /// (UNIXエポックからの秒数はf32では精度が足りないため)
+    pub fn after_duration(&self, start: Instant) -> Self {
+        self.at(start.elapsed().as_secs_f32())
+    }
+
+    /// 指定した経過秒数でのユニフォーム
+    pub fn at(&self, time: f32) -> Self {
         Self {
-            time: std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_secs_f32(),
-            _padding: [0; 12],
+            time,
+            motion_time: time,
+            ..*self
         }
     }
 
@@ -31,7 +51,7 @@ impl TimeUniform {
         let uniform_buffer = device.create_buffer(
             &wgpu::BufferDescriptor {
                 label: Some("Time Uniform"),
-                size: std::mem::size_of::<f32>() as u64,
+                size: std::mem::size_of::<Self>() as u64,
                 usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
                 mapped_at_creation: false
             }
@@ -50,7 +70,7 @@ impl TimeUniform {
                 ],
             }
         );
-        return (
+        (
             uniform_buffer,
             uniform_bindgroup
         )
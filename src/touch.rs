@@ -0,0 +1,89 @@
+use std::collections::BTreeMap;
+
+use winit::event::TouchPhase;
+
+/// タッチで行われた操作
+/// 位置は描画先の座標（-1.0..1.0）
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TouchGesture {
+    /// ほとんど動かさずに指を離した
+    Tap([f32; 2]),
+    /// 1本だけの指を `from` から `to` へ動かした
+    Drag { from: [f32; 2], to: [f32; 2] },
+    /// 2本の指の間隔が `factor` 倍になった（`center` は2本の指の中点）
+    Pinch { center: [f32; 2], factor: f32 },
+}
+
+struct TouchPoint {
+    position: [f32; 2],
+    /// 触れてから動いた距離の合計
+    travelled: f32,
+    /// 他の指と同時に触れていた（タップにはしない）
+    multi: bool,
+}
+
+/// 指ごとのタッチの状態を追い、操作に直す
+#[derive(Default)]
+pub struct TouchTracker {
+    /// 指の番号順に並べる（ピンチには番号の小さい2本を使う）
+    touches: BTreeMap<u64, TouchPoint>,
+}
+
+impl TouchTracker {
+    /// これより動いた指はタップとみなさない
+    const TAP_DISTANCE: f32 = 0.03;
+
+    /// タッチのイベントを受け取り、操作になったら返す
+    pub fn update(&mut self, id: u64, phase: TouchPhase, position: [f32; 2]) -> Option<TouchGesture> {
+        match phase {
+            TouchPhase::Started => {
+                self.touches.insert(id, TouchPoint { position, travelled: 0.0, multi: false });
+                if self.touches.len() > 1 {
+                    self.touches.values_mut().for_each(|touch| touch.multi = true);
+                }
+                None
+            }
+            TouchPhase::Moved => {
+                // ピンチの相手の指（動いた指が番号の小さい2本のどちらかの時）
+                let mut pinch_ids = self.touches.keys().take(2).copied();
+                let partner = match (pinch_ids.next(), pinch_ids.next()) {
+                    (Some(a), Some(b)) if a == id => self.touches.get(&b).map(|touch| touch.position),
+                    (Some(a), Some(b)) if b == id => self.touches.get(&a).map(|touch| touch.position),
+                    _ => None,
+                };
+                let single = self.touches.len() == 1;
+
+                let touch = self.touches.get_mut(&id)?;
+                let from = touch.position;
+                touch.position = position;
+                touch.travelled += distance(from, position);
+
+                if let Some(partner) = partner {
+                    let before = distance(from, partner);
+                    return (before > 1e-4).then(|| TouchGesture::Pinch {
+                        center: [(position[0] + partner[0]) * 0.5, (position[1] + partner[1]) * 0.5],
+                        factor: distance(position, partner) / before,
+                    });
+                }
+                single.then_some(TouchGesture::Drag { from, to: position })
+            }
+            TouchPhase::Ended => {
+                let touch = self.touches.remove(&id)?;
+                (!touch.multi && touch.travelled < Self::TAP_DISTANCE).then_some(TouchGesture::Tap(position))
+            }
+            TouchPhase::Cancelled => {
+                self.touches.remove(&id);
+                None
+            }
+        }
+    }
+
+    /// 今触れている指の位置
+    pub fn positions(&self) -> impl Iterator<Item = [f32; 2]> + '_ {
+        self.touches.values().map(|touch| touch.position)
+    }
+}
+
+fn distance(a: [f32; 2], b: [f32; 2]) -> f32 {
+    ((a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2)).sqrt()
+}
@@ -0,0 +1,37 @@
+struct TrailParams {
+    // 前のフレームまでの軌跡に掛ける割合
+    fade: f32,
+    // 前のフレームまでの軌跡から引く量（一定時間で必ず消えるようにする）
+    subtract: f32,
+}
+
+struct FragmentOutput {
+    // 後段（描画先またはブルーム）に渡す色
+    @location(0) color: vec4<f32>,
+    // 次のフレームで使う軌跡
+    @location(1) history: vec4<f32>,
+}
+
+@binding(0) @group(0) var frame: texture_2d<f32>;
+@binding(1) @group(0) var history: texture_2d<f32>;
+@binding(2) @group(0) var<uniform> params: TrailParams;
+
+// 画面全体を覆う三角形
+@vertex
+fn vertexMain(@builtin(vertex_index) vertexIdx: u32) -> @builtin(position) vec4<f32> {
+    let uv = vec2<f32>(f32((vertexIdx << 1u) & 2u), f32(vertexIdx & 2u));
+    return vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+}
+
+@fragment
+fn fragmentMain(@builtin(position) position: vec4<f32>) -> FragmentOutput {
+    let pixel = vec2<i32>(position.xy);
+    let current = textureLoad(frame, pixel, 0);
+    let faded = max(textureLoad(history, pixel, 0) * params.fade - vec4<f32>(params.subtract), vec4<f32>(0.0));
+
+    // 今のフレームの星を、薄れた軌跡の上に重ねる（乗算済みアルファ）
+    var out: FragmentOutput;
+    out.color = current + faded * (1.0 - current.a);
+    out.history = out.color;
+    return out;
+}
@@ -5,7 +5,25 @@ pub struct StarInstance {
     pub scale: f32,
     pub initial_rotation: f32,
     pub speed: [f32;2],
-    pub rotation_speed: f32
+    pub rotation_speed: f32,
+    /// 不透明度（0.0〜1.0）
+    pub opacity: f32,
+    /// またたきの位相（ラジアン）
+    pub twinkle_phase: f32,
+    /// またたきの周波数（Hz）。0なら一定の明るさ
+    pub twinkle_frequency: f32,
+    /// スプライトで描く時に使うアトラスのセルの番号
+    pub sprite: u32,
+    /// 奥行き（0.0が手前、1.0が奥）
+    pub depth: f32,
+    /// 3Dで描く時の回転軸（単位ベクトル）。2Dでは z 軸
+    pub rotation_axis: [f32; 3],
+    /// 3Dで描く時のカメラからの距離（0.0が手前、1.0が奥）
+    pub z: f32,
+    /// 3Dで描く時にカメラへ近づく速さ（奥行き全体を1とした単位/秒）
+    pub z_speed: f32,
+    /// メッシュとワープで描く時の色（リニアなRGB）
+    pub color: [f32; 3],
 }
 
 impl StarInstance {
@@ -38,30 +56,110 @@ impl StarInstance {
                 offset: 24,
                 shader_location: 6,
             },
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32,
+                offset: 28,
+                shader_location: 7,
+            },
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32,
+                offset: 32,
+                shader_location: 8,
+            },
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32,
+                offset: 36,
+                shader_location: 9,
+            },
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Uint32,
+                offset: 40,
+                shader_location: 10,
+            },
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32,
+                offset: 44,
+                shader_location: 11,
+            },
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32x3,
+                offset: 48,
+                shader_location: 12,
+            },
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32,
+                offset: 60,
+                shader_location: 13,
+            },
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32,
+                offset: 64,
+                shader_location: 14,
+            },
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32x3,
+                offset: 68,
+                shader_location: 15,
+            },
         ],
         step_mode: wgpu::VertexStepMode::Instance,
     };
+    /// 星の色の既定値（黄色）
+    pub const DEFAULT_COLOR: [f32; 3] = [1.0, 1.0, 0.0];
     pub fn new_vec(nums: u32) -> Vec<Self> {
-        use rand::Rng;
-        let mut rng = rand::thread_rng();
-        (0..nums)
-            .map(|_| StarInstance {
-                position: [rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0)],
-                scale: rng.gen_range(0.01..0.05),
-                initial_rotation: rng.gen_range(0.0..std::f32::consts::PI),
-                speed: [rng.gen_range(-0.01..0.01), rng.gen_range(-0.01..0.01)],
-                rotation_speed: rng.gen_range(-0.01..0.01),
+        Self::new_vec_with_rng(nums, &mut rand::thread_rng())
+    }
+    /// シードを固定して同じ配置を再現する
+    pub fn new_vec_seeded(nums: u32, seed: u64) -> Vec<Self> {
+        use rand::SeedableRng;
+        Self::new_vec_with_rng(nums, &mut rand::rngs::StdRng::seed_from_u64(seed))
+    }
+    pub fn new_vec_with_rng(nums: u32, rng: &mut impl rand::Rng) -> Vec<Self> {
+        let mut instances: Vec<Self> = (0..nums)
+            .map(|i| {
+                let position = [rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0)];
+                let scale = rng.gen_range(0.01..0.05);
+                StarInstance {
+                    position,
+                    scale,
+                    initial_rotation: rng.gen_range(0.0..std::f32::consts::PI),
+                    speed: [rng.gen_range(-0.01..0.01), rng.gen_range(-0.01..0.01)],
+                    rotation_speed: rng.gen_range(-0.01..0.01),
+                    opacity: rng.gen_range(0.6..1.0),
+                    twinkle_phase: rng.gen_range(0.0..std::f32::consts::TAU),
+                    twinkle_frequency: rng.gen_range(0.2..1.5),
+                    // アトラスのセルは順番に割り当てる（シェーダーでセルの数で割った余りを使う）
+                    sprite: i,
+                    depth: Self::depth_for_scale(scale),
+                    rotation_axis: [0.0, 0.0, 1.0],
+                    z: 0.0,
+                    z_speed: 0.0,
+                    color: Self::DEFAULT_COLOR,
+                }
             })
-            .collect()
+            .collect();
+        // 3D用の値は2D用の値を全て決めた後に決める（同じシードなら2Dの配置は変わらない）
+        for star in instances.iter_mut() {
+            let axis: [f32; 3] = [rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0)];
+            let length = axis.iter().map(|a| a * a).sum::<f32>().sqrt().max(1e-6);
+            star.rotation_axis = axis.map(|a| a / length);
+            star.z = rng.gen_range(0.0..1.0);
+            star.z_speed = rng.gen_range(0.05..0.2);
+        }
+        instances
+    }
+    /// 大きい星ほど手前にあるように見せる `depth`（大きさ 0.01..0.05 を 1.0..0.0 にする）
+    pub fn depth_for_scale(scale: f32) -> f32 {
+        (1.0 - (scale - 0.01) / 0.04).clamp(0.0, 1.0)
     }
-    pub fn get_buffer(device: &wgpu::Device,instances: &Vec<Self>) -> wgpu::Buffer {
+    pub fn get_buffer(device: &wgpu::Device,instances: &[Self]) -> wgpu::Buffer {
         use wgpu::util::DeviceExt;
-        return device.create_buffer_init(
+        device.create_buffer_init(
             &wgpu::util::BufferInitDescriptor {
                 label: None,
                 usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
-                contents: bytemuck::cast_slice(&instances)
+                contents: bytemuck::cast_slice(instances)
             }
-        );
+        )
     }
 }
\ No newline at end of file
@@ -0,0 +1,81 @@
+use super::position::PositionVertex;
+
+/// 法線付きの3Dの頂点
+#[repr(C)]
+#[derive(Clone, Copy,Debug,bytemuck::Pod,bytemuck::Zeroable)]
+pub struct MeshVertex {
+    pub position: [f32;3],
+    pub normal: [f32;3]
+}
+
+impl MeshVertex {
+    /* 頂点座標と法線の定義 */
+    pub const MESH_VERTEX_LAYOUT: wgpu::VertexBufferLayout<'static> = wgpu::VertexBufferLayout {
+        array_stride: std::mem::size_of::<Self>() as wgpu::BufferAddress,
+        attributes: &[
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32x3,
+                offset: 0,
+                shader_location: 0,
+            },
+            wgpu::VertexAttribute {
+                format: wgpu::VertexFormat::Float32x3,
+                offset: 12,
+                shader_location: 1,
+            }
+        ],
+        step_mode: wgpu::VertexStepMode::Vertex
+    };
+
+    /// `PositionVertex::new_vecs` の輪郭を厚さ `thickness` で押し出し、
+    /// 表と裏の中心を `bevel` だけ盛り上げた星のトライアングルリスト
+    /// 面ごとに法線を持たせるので、頂点は面の間で共有しない
+    pub fn new_extruded_star(nums: u32, thickness: f32, bevel: f32) -> Vec<Self> {
+        let outline = PositionVertex::new_vecs(nums);
+        let half = thickness * 0.5;
+        let front_center = [0.0, 0.0, half + bevel];
+        let back_center = [0.0, 0.0, -half - bevel];
+
+        let mut vertices = Vec::with_capacity(outline.len() * 12);
+        for (i, current) in outline.iter().enumerate() {
+            let next = outline[(i + 1) % outline.len()];
+            let [x0, y0] = current.position;
+            let [x1, y1] = next.position;
+            let front = ([x0, y0, half], [x1, y1, half]);
+            let back = ([x0, y0, -half], [x1, y1, -half]);
+
+            // 表と裏の斜面（輪郭は反時計回りなので、表は (中心, i, i+1) が反時計回り）
+            Self::push_triangle(&mut vertices, [front_center, front.0, front.1]);
+            Self::push_triangle(&mut vertices, [back_center, back.1, back.0]);
+            // 側面
+            Self::push_triangle(&mut vertices, [front.0, back.0, back.1]);
+            Self::push_triangle(&mut vertices, [front.0, back.1, front.1]);
+        }
+        vertices
+    }
+
+    /// 反時計回りの三角形を、面の法線を付けて追加する
+    fn push_triangle(vertices: &mut Vec<Self>, [a, b, c]: [[f32; 3]; 3]) {
+        let sub = |p: [f32; 3], q: [f32; 3]| [p[0] - q[0], p[1] - q[1], p[2] - q[2]];
+        let (u, v) = (sub(b, a), sub(c, a));
+        let cross = [
+            u[1] * v[2] - u[2] * v[1],
+            u[2] * v[0] - u[0] * v[2],
+            u[0] * v[1] - u[1] * v[0],
+        ];
+        let length = cross.iter().map(|n| n * n).sum::<f32>().sqrt().max(1e-6);
+        let normal = cross.map(|n| n / length);
+        vertices.extend([a, b, c].map(|position| Self { position, normal }));
+    }
+
+    pub fn get_buffer(device: &wgpu::Device, vertices: &[Self]) -> wgpu::Buffer {
+        use wgpu::util::DeviceExt;
+        device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("Mesh Vertex Buffer"),
+                usage: wgpu::BufferUsages::VERTEX,
+                contents: bytemuck::cast_slice(vertices)
+            }
+        )
+    }
+}
@@ -1,2 +1,3 @@
 pub mod position;
-pub mod instance;
\ No newline at end of file
+pub mod instance;
+pub mod mesh;
\ No newline at end of file
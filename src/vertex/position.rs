@@ -26,7 +26,7 @@ impl PositionVertex {
 
     pub fn new_vecs(nums: u32) -> Vec<Self> {
         use std::f32::consts::PI;
-        return (0..nums)
+        (0..nums)
             .map(|f| {
                 let radius = if f % 2 == 0 {
                     1.0
@@ -34,7 +34,7 @@ impl PositionVertex {
                     0.38
                 };
         
-                let angle = f as f32 * PI / 5 as f32;
+                let angle = f as f32 * PI / 5_f32;
                 Self {
                     position: [
                         PositionVertex::CENTER.position[0] + angle.cos() * radius,
@@ -45,11 +45,20 @@ impl PositionVertex {
             .collect()
     }
 
-    pub fn vertices_byte(vertices: &Vec<Self>) -> &[u8] {
+    /// 外周の頂点と中心を交互に並べたトライアングルストリップ
+    /// 奇数番目の三角形 (外周i, 中心, 外周i+1) が反時計回りになり、偶数番目は面積0になる
+    pub fn new_strip(nums: u32) -> Vec<Self> {
+        let outline = Self::new_vecs(nums);
+        outline
+            .iter()
+            .chain(outline.first())
+            .flat_map(|vertex| [Self::CENTER, *vertex])
+            .collect()
+    }
+
+    pub fn vertices_byte(vertices: &[Self]) -> &[u8] {
         use bytemuck;
-        return bytemuck::cast_slice(
-            vertices.as_slice()
-        );
+        bytemuck::cast_slice(vertices)
     }
 
     pub fn get_buffer(device: &wgpu::Device,vertices_bytes: &[u8]) -> wgpu::Buffer {
@@ -0,0 +1,102 @@
+struct Uniforms {
+    time: f32,
+    // 移動と回転に使う時間（CPUで動かす時は0）
+    motionTime: f32,
+    // 星空を拡大率 viewZoom で拡大してから viewOffset だけずらす
+    viewOffset: vec2<f32>,
+    viewZoom: f32,
+}
+
+struct Camera {
+    viewProjection: mat4x4<f32>,
+    // 星が現れる奥行きの範囲（カメラからの距離）
+    near: f32,
+    far: f32,
+    // 画面上の -1.0..1.0 を3Dの空間でどれだけの広さに置くか
+    spread: f32,
+}
+
+struct InstanceInput {
+    @location(2) position: vec2<f32>,
+    @location(3) scale: f32,
+    @location(4) initialRotation: f32,
+    @location(5) speed: vec2<f32>,
+    @location(6) rotationSpeed: f32,
+    @location(7) opacity: f32,
+    @location(8) twinklePhase: f32,
+    @location(9) twinkleFrequency: f32,
+    @location(12) rotationAxis: vec3<f32>,
+    // カメラからの距離（0が手前、1が奥）
+    @location(13) z: f32,
+    @location(14) zSpeed: f32,
+    @location(15) color: vec3<f32>,
+}
+
+@binding(0) @group(0) var<uniform> uniforms: Uniforms;
+@binding(1) @group(0) var<uniform> camera: Camera;
+
+const TAU: f32 = 6.28318531;
+// またたきで暗くなる最大の割合
+const TWINKLE_DEPTH: f32 = 0.6;
+// 奥のこの割合より先では、急に現れないように薄くする
+const FADE_START: f32 = 0.8;
+
+// -1.0..1.0 の範囲でラップする（CPUの simulation::wrap と同じ）
+fn wrap(value: f32) -> f32 {
+    return (value + 1.0) - 2.0 * floor((value + 1.0) / 2.0) - 1.0;
+}
+
+// またたきを含めた不透明度
+fn starAlpha(instance: InstanceInput) -> f32 {
+    let wave = 0.5 + 0.5 * sin(uniforms.time * instance.twinkleFrequency * TAU + instance.twinklePhase);
+    let twinkle = select(1.0, 1.0 - TWINKLE_DEPTH * wave, instance.twinkleFrequency > 0.0);
+    return clamp(instance.opacity * twinkle, 0.0, 1.0);
+}
+
+// 軸 axis の周りに angle だけ回す（ロドリゲスの回転公式）
+fn rotateAxisAngle(v: vec3<f32>, axis: vec3<f32>, angle: f32) -> vec3<f32> {
+    let c = cos(angle);
+    let s = sin(angle);
+    return v * c + cross(axis, v) * s + axis * dot(axis, v) * (1.0 - c);
+}
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) alpha: f32,
+    @location(1) color: vec3<f32>,
+}
+
+@vertex
+fn vertexMain(
+    @location(0) position: vec2<f32>,
+    instance: InstanceInput,
+) -> VertexOutput {
+    let rotation = instance.initialRotation + uniforms.motionTime * instance.rotationSpeed;
+    let axis = normalize(instance.rotationAxis);
+    let local = rotateAxisAngle(vec3<f32>(position * instance.scale * camera.spread, 0.0), axis, rotation);
+
+    // 横方向の動きは2Dと同じようにラップする
+    let moveX = instance.position.x + instance.speed.x * uniforms.motionTime;
+    let moveY = instance.position.y + instance.speed.y * uniforms.motionTime;
+    let wrappedX = wrap(moveX);
+    let wrappedY = wrap(moveY);
+
+    // 奥から手前へ進み、手前を過ぎたら奥に戻る
+    let z = fract(instance.z - instance.zSpeed * uniforms.motionTime);
+    let distance = mix(camera.near, camera.far, z);
+    let world = vec3<f32>(vec2<f32>(wrappedX, wrappedY) * camera.spread, -distance) + local;
+
+    var out: VertexOutput;
+    let clip = camera.viewProjection * vec4<f32>(world, 1.0);
+    // 拡大とずらしは投影した後の画面上で行う
+    out.position = vec4<f32>(clip.xy * uniforms.viewZoom + uniforms.viewOffset * clip.w, clip.zw);
+    out.color = instance.color;
+    out.alpha = starAlpha(instance) * (1.0 - smoothstep(FADE_START, 1.0, z));
+    return out;
+}
+
+@fragment
+fn fragmentMain(in: VertexOutput) -> @location(0) vec4<f32> {
+    // メッシュでの描画と同じく星の色で塗りつぶす（乗算済みアルファ）
+    return vec4<f32>(in.color, 1.0) * in.alpha;
+}
@@ -0,0 +1,876 @@
+//! 星の描画結果を固定のシードと時刻で描き、参照画像と比較するテスト
+//!
+//! 機能ごとにテストを分け、場面ごとに新しいコンテキストで描く。
+//! GPUによって結果が変わらないように、ソフトウェアのアダプタで描く。
+//!
+//! 参照画像は `tests/golden/*.png` にある。描画を意図して変えた時は
+//! `GOLDEN_BLESS=1 cargo test --test golden` で作り直す。
+//! 失敗した時は `target/golden-diff/` に実際の画像と差分画像を書き出す。
+
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use wasm_only_draws::boids::BoidsSettings;
+use wasm_only_draws::ctx::background::{Background, BackgroundSettings};
+use wasm_only_draws::ctx::bloom::BloomSettings;
+use wasm_only_draws::ctx::depth::StarOrdering;
+use wasm_only_draws::ctx::sdf_star::{SdfStyle, StarRenderMode};
+use wasm_only_draws::ctx::sprite::SpriteAtlas;
+use wasm_only_draws::ctx::trails::TrailSettings;
+use wasm_only_draws::ctx::wgpu_star_ctx::WgpuStarCtx;
+use wasm_only_draws::curves::LifetimeCurves;
+use wasm_only_draws::emitter::{Emitter, EmitterShape, EmitterSystem, ValueRange};
+use wasm_only_draws::fireworks::{Fireworks, FireworksSettings};
+use wasm_only_draws::forces::ForceField;
+use wasm_only_draws::interaction;
+use wasm_only_draws::simulation::{EdgeMode, SimulationMode};
+use wasm_only_draws::touch::{TouchGesture, TouchTracker};
+use wasm_only_draws::vertex::instance::StarInstance;
+
+const SIZE: u32 = 256;
+/// この値より大きく色が違う画素を「違う」とみなす（0〜255）
+const CHANNEL_TOLERANCE: u8 = 8;
+/// 違う画素がこの割合を超えたら失敗とする
+const MAX_DIFF_RATIO: f64 = 0.005;
+
+struct Scene {
+    name: &'static str,
+    instances: Vec<StarInstance>,
+    time: f32,
+    /// `time` の前に描いておくフレームの時刻（軌跡を残すため）
+    previous_times: Vec<f32>,
+    sample_count: u32,
+    render_mode: StarRenderMode,
+    sdf_style: SdfStyle,
+    sprite_atlas: Option<SpriteAtlas>,
+    bloom: Option<BloomSettings>,
+    trails: Option<TrailSettings>,
+    background: BackgroundSettings,
+    ordering: StarOrdering,
+    simulation: SimulationMode,
+    /// 星を設定した後、描く前に行う操作
+    action: Option<fn(&mut WgpuStarCtx)>,
+}
+
+impl Scene {
+    fn new(name: &'static str, instances: Vec<StarInstance>) -> Self {
+        Self {
+            name,
+            instances,
+            time: 0.0,
+            previous_times: Vec::new(),
+            sample_count: 1,
+            render_mode: StarRenderMode::Mesh,
+            sdf_style: SdfStyle::default(),
+            sprite_atlas: None,
+            bloom: None,
+            trails: None,
+            background: BackgroundSettings::default(),
+            ordering: StarOrdering::default(),
+            simulation: SimulationMode::Gpu,
+            action: None,
+        }
+    }
+}
+
+fn single_star(rotation: f32) -> Vec<StarInstance> {
+    vec![StarInstance {
+        position: [0.0, 0.0],
+        scale: 0.8,
+        initial_rotation: rotation,
+        speed: [0.0, 0.0],
+        rotation_speed: 0.0,
+        opacity: 1.0,
+        twinkle_phase: 0.0,
+        twinkle_frequency: 0.0,
+        sprite: 0,
+        depth: 0.0,
+        rotation_axis: [0.0, 0.0, 1.0],
+        z: 0.0,
+        z_speed: 0.0,
+        color: StarInstance::DEFAULT_COLOR,
+    }]
+}
+
+/// 半透明の星を重ねる
+fn overlapping_translucent_stars() -> Vec<StarInstance> {
+    [(-0.2, 0.5, 0.0), (0.2, 0.5, 0.0), (0.0, 1.0, 0.25)]
+        .into_iter()
+        .map(|(x, opacity, twinkle_phase)| StarInstance {
+            position: [x, 0.0],
+            opacity,
+            twinkle_phase,
+            twinkle_frequency: if twinkle_phase > 0.0 { 0.5 } else { 0.0 },
+            ..single_star(0.0)[0]
+        })
+        .collect()
+}
+
+/// 横に速く動く星
+fn moving_stars() -> Vec<StarInstance> {
+    [(-0.6, 0.4), (-0.4, -0.3)]
+        .into_iter()
+        .map(|(x, y)| StarInstance {
+            position: [x, y],
+            scale: 0.15,
+            speed: [1.0, 0.2],
+            rotation_speed: 2.0,
+            ..single_star(0.0)[0]
+        })
+        .collect()
+}
+
+fn background(background: Background, parallax_layers: u32) -> BackgroundSettings {
+    BackgroundSettings {
+        background,
+        parallax_layers,
+        camera_velocity: [0.1, 0.05],
+    }
+}
+
+/// 背景に貼る画像（市松模様）を書き出してパスを返す
+fn background_image() -> PathBuf {
+    let pixels: Vec<u8> = (0..SIZE * SIZE)
+        .flat_map(|i| {
+            let (x, y) = (i % SIZE, i / SIZE);
+            if (x / 32 + y / 32) % 2 == 0 { [40, 60, 120, 255] } else { [x as u8, 20, y as u8, 255] }
+        })
+        .collect();
+    let path = diff_dir().join("background.png");
+    std::fs::create_dir_all(diff_dir()).unwrap();
+    write_png(&path, &pixels);
+    path
+}
+
+/// 2x2 のセルに円・ひし形・輪・四角を描いたアトラスを書き出して読み込む
+/// 並行して走るテストが書きかけのファイルを読まないように、書き出すのは一度だけにする
+fn sprite_atlas() -> SpriteAtlas {
+    static PATH: OnceLock<PathBuf> = OnceLock::new();
+    SpriteAtlas::load(PATH.get_or_init(write_sprite_atlas), 2, 2).unwrap()
+}
+
+fn write_sprite_atlas() -> PathBuf {
+    let cell = SIZE / 2;
+    let pixels: Vec<u8> = (0..SIZE * SIZE)
+        .flat_map(|i| {
+            let (x, y) = (i % SIZE, i / SIZE);
+            let (dx, dy) = (
+                (x % cell) as f32 / cell as f32 * 2.0 - 1.0,
+                (y % cell) as f32 / cell as f32 * 2.0 - 1.0,
+            );
+            let r = (dx * dx + dy * dy).sqrt();
+            let (inside, color) = match (x / cell, y / cell) {
+                (0, 0) => (r < 0.8, [255, 80, 80]),
+                (1, 0) => (dx.abs() + dy.abs() < 0.9, [80, 255, 80]),
+                (0, 1) => ((0.5..0.9).contains(&r), [80, 160, 255]),
+                _ => (dx.abs().max(dy.abs()) < 0.6, [255, 255, 255]),
+            };
+            if inside { [color[0], color[1], color[2], 255] } else { [0, 0, 0, 0] }
+        })
+        .collect();
+    let path = diff_dir().join("sprite_atlas.png");
+    std::fs::create_dir_all(diff_dir()).unwrap();
+    write_png(&path, &pixels);
+    path
+}
+
+/// アトラスの4つのセルを1つずつ使う星
+fn sprite_stars() -> Vec<StarInstance> {
+    [(-0.5, 0.5), (0.5, 0.5), (-0.5, -0.5), (0.5, -0.5)]
+        .into_iter()
+        .enumerate()
+        .map(|(sprite, (x, y))| StarInstance {
+            position: [x, y],
+            scale: 0.4,
+            sprite: sprite as u32,
+            ..single_star(0.3)[0]
+        })
+        .collect()
+}
+
+/// 手前の大きな星（赤い円）を先に、奥の小さな星（緑のひし形）を後に並べ、一部を重ねる
+fn near_and_far_stars() -> Vec<StarInstance> {
+    [(-0.15, 0.5, 0, 0.1), (0.25, 0.35, 1, 0.9)]
+        .into_iter()
+        .map(|(x, scale, sprite, depth)| StarInstance {
+            position: [x, 0.0],
+            scale,
+            sprite,
+            depth,
+            ..single_star(0.0)[0]
+        })
+        .collect()
+}
+
+/// 手前（赤）と奥（緑）に重ねて置いた3Dの星
+/// 2Dの `depth` は逆にして、`depth` で並べ替えると奥の星を後に描くようにする
+fn near_and_far_3d_stars() -> Vec<StarInstance> {
+    [([0.0, 0.0], 0.1, 1.0, [1.0, 0.2, 0.2]), ([0.1, 0.05], 0.3, 0.0, [0.2, 1.0, 0.2])]
+        .into_iter()
+        .map(|(position, z, depth, color)| StarInstance {
+            position,
+            scale: 0.15,
+            z,
+            depth,
+            color,
+            ..single_star(0.0)[0]
+        })
+        .collect()
+}
+
+/// カメラの近くで斜めに傾けた立体の星
+fn lit_star() -> Vec<StarInstance> {
+    vec![StarInstance {
+        scale: 0.1,
+        initial_rotation: 0.8,
+        rotation_axis: [0.6, 0.8, 0.0],
+        z: 0.05,
+        ..single_star(0.0)[0]
+    }]
+}
+
+/// カーソルの位置に星を引き寄せる
+fn attract_to_pointer(ctx: &mut WgpuStarCtx) {
+    ctx.interaction.strength = 4.0;
+    ctx.pointer = Some([0.3, 0.2]);
+}
+
+/// 中央の星を消し、右下に星を飛び散らせる
+fn remove_and_burst(ctx: &mut WgpuStarCtx) {
+    use rand::SeedableRng;
+    ctx.interaction.remove_radius = 0.4;
+    ctx.remove_stars_near([0.0, 0.0]);
+    ctx.add_instances(interaction::spawn_burst([0.5, -0.5], 12, &mut rand::rngs::StdRng::seed_from_u64(7)));
+}
+
+/// 正面からぶつかる2つの星と、端で跳ね返る星
+fn bouncing_stars() -> Vec<StarInstance> {
+    [([-0.5, 0.0], [1.0, 0.0], 0.15), ([0.5, 0.0], [-1.0, 0.0], 0.15), ([0.7, 0.6], [1.0, 0.3], 0.1)]
+        .into_iter()
+        .map(|(position, speed, scale)| StarInstance {
+            position,
+            speed,
+            scale,
+            ..single_star(0.0)[0]
+        })
+        .collect()
+}
+
+/// 重力で落ちながら、風に流され、渦に巻き込まれる
+fn gravity_wind_and_vortex(ctx: &mut WgpuStarCtx) {
+    ctx.forces = vec![
+        ForceField::Gravity([0.0, -0.5]),
+        ForceField::Wind { velocity: [0.3, 0.0], turbulence: 0.5 },
+        ForceField::Vortex { center: [0.0, 0.0], strength: 1.5, radius: 0.6 },
+        ForceField::Point { center: [0.6, 0.6], strength: -1.0, radius: 0.4 },
+    ];
+}
+
+/// 4種類のエミッターから星を生み出す。枠が足りないので古い星から入れ替わる
+fn four_emitters(ctx: &mut WgpuStarCtx) {
+    let burst = Emitter {
+        rate: 0.0,
+        burst_count: 24,
+        speed: ValueRange::new(0.3, 0.5),
+        ..Emitter::new(EmitterShape::Point([-0.5, 0.5]))
+    };
+    let fountain = Emitter {
+        rate: 40.0,
+        direction: ValueRange::new(80f32.to_radians(), 100f32.to_radians()),
+        lifetime: ValueRange::new(0.5, 1.0),
+        ..Emitter::new(EmitterShape::Line { from: [0.2, -0.9], to: [0.8, -0.9] })
+    };
+    let ring = Emitter {
+        rate: 30.0,
+        speed: ValueRange::new(0.0, 0.05),
+        ..Emitter::new(EmitterShape::Circle { center: [0.5, 0.4], radius: 0.3 })
+    };
+    let rect = Emitter {
+        rate: 20.0,
+        speed: ValueRange::new(0.0, 0.0),
+        scale: ValueRange::new(0.04, 0.06),
+        ..Emitter::new(EmitterShape::Rect { min: [-0.9, -0.9], max: [-0.3, -0.3] })
+    };
+    ctx.set_emitters(EmitterSystem::seeded(vec![burst, fountain, ring, rect], 96, 3));
+}
+
+/// 生まれた星が膨らんでから縮み、白から赤へ変わりながら速く回って消える
+fn emitter_with_curves(ctx: &mut WgpuStarCtx) {
+    ctx.curves = Some(LifetimeCurves::parse(
+        "# 寿命に合わせた曲線\n\
+         scale = 0:0.5 ease-out 0.3:2 ease-in 1:0.2\n\
+         opacity = 0:1 bezier(0.7,0,0.9,0.4) 1:0\n\
+         color = 0:#ffffff ease-in-out 0.5:#ffff00 1:#ff2000\n\
+         rotation-speed = 0:1 linear 1:6\n",
+    ));
+    let fountain = Emitter {
+        rate: 60.0,
+        burst_count: 10,
+        direction: ValueRange::new(60f32.to_radians(), 120f32.to_radians()),
+        speed: ValueRange::new(0.4, 0.7),
+        lifetime: ValueRange::new(1.5, 2.0),
+        scale: ValueRange::new(0.04, 0.06),
+        ..Emitter::new(EmitterShape::Point([0.0, -0.8]))
+    };
+    ctx.set_emitters(EmitterSystem::seeded(vec![fountain], 256, 5));
+}
+
+/// 打ち上げた花火が弾け、いくつかの火花がもう一度弾ける
+fn launch_fireworks(ctx: &mut WgpuStarCtx) {
+    let settings = FireworksSettings {
+        launch_rate: 2.0,
+        ..FireworksSettings::default()
+    };
+    ctx.edges = EdgeMode::Vanish;
+    ctx.set_fireworks(Fireworks::seeded(settings, 1024, 11));
+}
+
+fn bounce_and_collide(ctx: &mut WgpuStarCtx) {
+    ctx.edges = EdgeMode::Bounce;
+    ctx.collisions = true;
+}
+
+/// 2本の指で右上を拡大してから、1本の指で星をなぞって動かす
+fn pinch_and_drag(ctx: &mut WgpuStarCtx) {
+    use winit::event::TouchPhase;
+    let mut touch = TouchTracker::default();
+    touch.update(1, TouchPhase::Started, [0.2, 0.5]);
+    touch.update(2, TouchPhase::Started, [0.6, 0.5]);
+    let Some(TouchGesture::Pinch { center, factor }) = touch.update(2, TouchPhase::Moved, [1.0, 0.5]) else {
+        panic!("moving one of two touches should pinch");
+    };
+    assert!((factor - 2.0).abs() < 1e-4, "pinch factor {}", factor);
+    ctx.view.zoom_about(center, factor);
+    assert_eq!(touch.update(1, TouchPhase::Ended, [0.2, 0.5]), None);
+    assert_eq!(touch.update(2, TouchPhase::Ended, [1.0, 0.5]), None);
+
+    touch.update(3, TouchPhase::Started, [-0.5, -0.5]);
+    let Some(TouchGesture::Drag { from, to }) = touch.update(3, TouchPhase::Moved, [0.0, -0.5]) else {
+        panic!("a single moving touch should drag");
+    };
+    ctx.drag_stars(ctx.view.to_scene(from), ctx.view.to_scene(to));
+    assert_eq!(touch.update(3, TouchPhase::Ended, [0.0, -0.5]), None);
+}
+
+fn golden_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("golden")
+}
+
+fn diff_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("target").join("golden-diff")
+}
+
+fn write_png(path: &Path, pixels: &[u8]) {
+    let file = std::fs::File::create(path).unwrap();
+    let mut encoder = png::Encoder::new(std::io::BufWriter::new(file), SIZE, SIZE);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header().unwrap().write_image_data(pixels).unwrap();
+}
+
+fn read_png(path: &Path) -> Option<Vec<u8>> {
+    let decoder = png::Decoder::new(std::fs::File::open(path).ok()?);
+    let mut reader = decoder.read_info().unwrap();
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut pixels).unwrap();
+    assert_eq!((info.width, info.height), (SIZE, SIZE), "{} has wrong size", path.display());
+    assert_eq!(info.color_type, png::ColorType::Rgba, "{} is not RGBA", path.display());
+    pixels.truncate(info.buffer_size());
+    Some(pixels)
+}
+
+/// 違う画素の数と、違う画素を赤く塗った差分画像を返す
+fn compare(expected: &[u8], actual: &[u8]) -> (usize, Vec<u8>) {
+    let mut differing = 0;
+    let mut diff = Vec::with_capacity(actual.len());
+    for (expected, actual) in expected.chunks_exact(4).zip(actual.chunks_exact(4)) {
+        let max_delta = expected
+            .iter()
+            .zip(actual)
+            .map(|(e, a)| e.abs_diff(*a))
+            .max()
+            .unwrap_or(0);
+        if max_delta > CHANNEL_TOLERANCE {
+            differing += 1;
+            diff.extend_from_slice(&[255, 0, 0, 255]);
+        } else {
+            // 一致している画素は薄く表示する
+            let gray = (actual[..3].iter().map(|c| *c as u32).sum::<u32>() / 12) as u8;
+            diff.extend_from_slice(&[gray, gray, gray, 255]);
+        }
+    }
+    (differing, diff)
+}
+
+/// 描画結果の `at`（-1.0..1.0、上が +y）にある画素の色
+fn pixel_at(pixels: &[u8], at: [f32; 2]) -> [u8; 4] {
+    let x = (((at[0] + 1.0) * 0.5 * SIZE as f32) as u32).min(SIZE - 1);
+    let y = (((1.0 - at[1]) * 0.5 * SIZE as f32) as u32).min(SIZE - 1);
+    let index = ((y * SIZE + x) * 4) as usize;
+    pixels[index..index + 4].try_into().unwrap()
+}
+
+/// 新しいコンテキストで場面を描き、描画結果を返す
+fn render(scene: Scene) -> Vec<u8> {
+    let mut ctx = WgpuStarCtx::new_software(SIZE, SIZE);
+    ctx.set_background(scene.background);
+    ctx.set_bloom(scene.bloom);
+    ctx.set_trails(scene.trails);
+    assert_eq!(ctx.set_sample_count(scene.sample_count), scene.sample_count);
+    ctx.render_mode = scene.render_mode;
+    if let Some(atlas) = &scene.sprite_atlas {
+        ctx.set_sprite_atlas(atlas);
+    }
+    ctx.sdf.style = scene.sdf_style;
+    ctx.set_ordering(scene.ordering);
+    ctx.simulation = scene.simulation;
+    ctx.set_instances(scene.instances);
+    if let Some(action) = scene.action {
+        action(&mut ctx);
+    }
+    for time in scene.previous_times {
+        ctx.draw_at(time);
+    }
+    ctx.draw_at(scene.time);
+    ctx.read_pixels().unwrap()
+}
+
+/// 場面を描いて参照画像と比べる（`GOLDEN_BLESS` がある時は参照画像を書き出す）
+fn assert_matches_golden_images(scenes: Vec<Scene>) {
+    let bless = std::env::var_os("GOLDEN_BLESS").is_some();
+    let mut failures = Vec::new();
+
+    for scene in scenes {
+        let name = scene.name;
+        let actual = render(scene);
+
+        let golden_path = golden_dir().join(format!("{}.png", name));
+        if bless {
+            std::fs::create_dir_all(golden_dir()).unwrap();
+            write_png(&golden_path, &actual);
+            continue;
+        }
+        let Some(expected) = read_png(&golden_path) else {
+            failures.push(format!("{}: missing {}", name, golden_path.display()));
+            continue;
+        };
+
+        let (differing, diff) = compare(&expected, &actual);
+        let ratio = differing as f64 / (SIZE * SIZE) as f64;
+        if ratio > MAX_DIFF_RATIO {
+            std::fs::create_dir_all(diff_dir()).unwrap();
+            write_png(&diff_dir().join(format!("{}-actual.png", name)), &actual);
+            write_png(&diff_dir().join(format!("{}-diff.png", name)), &diff);
+            failures.push(format!(
+                "{}: {} pixels ({:.2}%) differ, see {}",
+                name,
+                differing,
+                ratio * 100.0,
+                diff_dir().display()
+            ));
+        }
+    }
+
+    assert!(failures.is_empty(), "golden image mismatch:\n{}", failures.join("\n"));
+}
+
+#[test]
+fn mesh_stars() {
+    assert_matches_golden_images(vec![
+        Scene::new("single_star", single_star(0.0)),
+        Scene::new("single_star_rotated", single_star(std::f32::consts::FRAC_PI_2)),
+        Scene::new("field_seed_42", StarInstance::new_vec_seeded(200, 42)),
+        Scene {
+            time: 30.0,
+            ..Scene::new("field_seed_42_t30", StarInstance::new_vec_seeded(200, 42))
+        },
+    ]);
+}
+
+#[test]
+fn msaa() {
+    assert_matches_golden_images(vec![Scene {
+        sample_count: 4,
+        ..Scene::new("single_star_msaa4", single_star(0.3))
+    }]);
+}
+
+/// 下げた後のサンプル数でテクスチャとパイプラインを作っても検証エラーにならない
+#[test]
+fn every_requested_sample_count_is_valid() {
+    let mut ctx = WgpuStarCtx::new_software(SIZE, SIZE);
+    ctx.set_instances(single_star(0.3));
+    for requested in [1, 2, 4, 8] {
+        ctx.device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let sample_count = ctx.set_sample_count(requested);
+        ctx.draw_at(0.0);
+        let error = pollster::block_on(ctx.device.pop_error_scope());
+        assert!(sample_count <= requested, "x{} became x{}", requested, sample_count);
+        assert!(error.is_none(), "x{} (requested x{}): {:?}", sample_count, requested, error);
+    }
+}
+
+#[test]
+fn sdf_stars() {
+    let mut glow = SdfStyle::default();
+    glow.glow_strength = 0.8;
+    glow.outline_width = 0.05;
+    assert_matches_golden_images(vec![
+        Scene {
+            render_mode: StarRenderMode::Sdf,
+            ..Scene::new("single_star_sdf", single_star(0.3))
+        },
+        Scene {
+            render_mode: StarRenderMode::Sdf,
+            sdf_style: glow,
+            ..Scene::new("single_star_sdf_glow", single_star(0.3))
+        },
+        Scene {
+            render_mode: StarRenderMode::Sdf,
+            ..Scene::new("field_seed_42_sdf", StarInstance::new_vec_seeded(200, 42))
+        },
+    ]);
+}
+
+#[test]
+fn translucent_twinkle() {
+    assert_matches_golden_images(vec![Scene {
+        time: 1.0,
+        ..Scene::new("translucent_twinkle", overlapping_translucent_stars())
+    }]);
+}
+
+#[test]
+fn bloom() {
+    assert_matches_golden_images(vec![
+        Scene {
+            bloom: Some(BloomSettings::default()),
+            ..Scene::new("single_star_bloom", single_star(0.3))
+        },
+        Scene {
+            sample_count: 4,
+            bloom: Some(BloomSettings { intensity: 2.0, threshold: 0.3, radius: 8.0 }),
+            ..Scene::new("field_seed_42_bloom_msaa4", StarInstance::new_vec_seeded(200, 42))
+        },
+    ]);
+}
+
+#[test]
+fn trails() {
+    assert_matches_golden_images(vec![
+        Scene {
+            time: 0.6,
+            previous_times: (0..6).map(|frame| frame as f32 * 0.1).collect(),
+            trails: Some(TrailSettings { length: 0.5, decay: 0.2 }),
+            ..Scene::new("moving_stars_trails", moving_stars())
+        },
+        Scene {
+            time: 0.6,
+            previous_times: (0..6).map(|frame| frame as f32 * 0.1).collect(),
+            sample_count: 4,
+            bloom: Some(BloomSettings::default()),
+            trails: Some(TrailSettings { length: 0.5, decay: 0.2 }),
+            ..Scene::new("moving_stars_trails_bloom_msaa4", moving_stars())
+        },
+    ]);
+}
+
+#[test]
+fn backgrounds() {
+    assert_matches_golden_images(vec![
+        Scene {
+            background: background(Background::parse("solid:#101830").unwrap(), 0),
+            ..Scene::new("background_solid", single_star(0.3))
+        },
+        Scene {
+            background: background(Background::parse("vertical:#000010,#402060").unwrap(), 3),
+            time: 2.0,
+            ..Scene::new("background_vertical_parallax", single_star(0.3))
+        },
+        Scene {
+            background: background(Background::parse("radial:#203060,#00000080").unwrap(), 0),
+            sample_count: 4,
+            ..Scene::new("background_radial_msaa4", single_star(0.3))
+        },
+        Scene {
+            background: background(Background::Image(background_image()), 0),
+            ..Scene::new("background_image", single_star(0.3))
+        },
+        Scene {
+            time: 0.6,
+            previous_times: (0..6).map(|frame| frame as f32 * 0.1).collect(),
+            trails: Some(TrailSettings { length: 0.5, decay: 0.2 }),
+            background: background(Background::parse("vertical:#000010,#402060").unwrap(), 2),
+            ..Scene::new("background_with_trails", moving_stars())
+        },
+    ]);
+}
+
+#[test]
+fn sprites() {
+    assert_matches_golden_images(vec![
+        Scene {
+            render_mode: StarRenderMode::Sprite,
+            sprite_atlas: Some(sprite_atlas()),
+            ..Scene::new("sprite_atlas_cells", sprite_stars())
+        },
+        Scene {
+            render_mode: StarRenderMode::Sprite,
+            sprite_atlas: Some(sprite_atlas()),
+            ..Scene::new("field_seed_42_sprites", StarInstance::new_vec_seeded(200, 42))
+        },
+    ]);
+}
+
+#[test]
+fn depth_ordering() {
+    assert_matches_golden_images(vec![
+        Scene {
+            render_mode: StarRenderMode::Sprite,
+            sprite_atlas: Some(sprite_atlas()),
+            ..Scene::new("depth_sorted", near_and_far_stars())
+        },
+        Scene {
+            render_mode: StarRenderMode::Sprite,
+            sprite_atlas: Some(sprite_atlas()),
+            sample_count: 4,
+            ordering: StarOrdering::DepthBuffer,
+            ..Scene::new("depth_buffer_msaa4", near_and_far_stars())
+        },
+    ]);
+}
+
+#[test]
+fn warp_stars() {
+    assert_matches_golden_images(vec![
+        // 既定の並べ方でも深度バッファで前後を決める
+        Scene {
+            render_mode: StarRenderMode::Warp,
+            time: 2.0,
+            ..Scene::new("field_seed_42_warp", StarInstance::new_vec_seeded(200, 42))
+        },
+        Scene {
+            render_mode: StarRenderMode::Warp,
+            ..Scene::new("warp_near_covers_far", near_and_far_3d_stars())
+        },
+        Scene {
+            render_mode: StarRenderMode::Warp,
+            ordering: StarOrdering::DepthBuffer,
+            sample_count: 4,
+            time: 2.0,
+            ..Scene::new("field_seed_42_warp_msaa4", StarInstance::new_vec_seeded(200, 42))
+        },
+        Scene {
+            render_mode: StarRenderMode::Warp,
+            time: 2.0,
+            action: Some(|ctx| ctx.view.zoom_about([0.0, 0.0], 1.5)),
+            ..Scene::new("field_seed_42_warp_zoomed", StarInstance::new_vec_seeded(200, 42))
+        },
+    ]);
+}
+
+#[test]
+fn lit_stars() {
+    assert_matches_golden_images(vec![
+        Scene {
+            render_mode: StarRenderMode::Lit,
+            ordering: StarOrdering::DepthBuffer,
+            sample_count: 4,
+            time: 2.0,
+            ..Scene::new("field_seed_42_lit_msaa4", StarInstance::new_vec_seeded(200, 42))
+        },
+        // 既定の並べ方でも、面どうしと星どうしの前後を深度バッファで決める
+        Scene {
+            render_mode: StarRenderMode::Lit,
+            time: 2.0,
+            ..Scene::new("field_seed_42_lit", StarInstance::new_vec_seeded(200, 42))
+        },
+        Scene {
+            render_mode: StarRenderMode::Lit,
+            ..Scene::new("lit_star_tilted", lit_star())
+        },
+        Scene {
+            render_mode: StarRenderMode::Lit,
+            ..Scene::new("lit_near_covers_far", near_and_far_3d_stars())
+        },
+    ]);
+}
+
+#[test]
+fn mouse_interaction() {
+    assert_matches_golden_images(vec![
+        Scene {
+            simulation: SimulationMode::Cpu,
+            action: Some(attract_to_pointer),
+            time: 1.0,
+            previous_times: (0..10).map(|frame| frame as f32 * 0.1).collect(),
+            ..Scene::new("field_seed_42_attract", StarInstance::new_vec_seeded(200, 42))
+        },
+        Scene {
+            action: Some(remove_and_burst),
+            time: 0.5,
+            ..Scene::new("field_seed_42_remove_and_burst", StarInstance::new_vec_seeded(200, 42))
+        },
+    ]);
+}
+
+#[test]
+fn burst_after_a_long_run_stays_on_screen() {
+    let mut ctx = WgpuStarCtx::new_software(SIZE, SIZE);
+    let mut hidden = single_star(0.0);
+    hidden[0].opacity = 0.0;
+    ctx.set_instances(hidden);
+    ctx.draw_at(10.0);
+    ctx.spawn_burst([0.5, -0.5]);
+    ctx.draw_at(10.0);
+    let pixels = ctx.read_pixels().unwrap();
+    assert_ne!(pixel_at(&pixels, [0.5, -0.5]), [0, 0, 0, 0], "burst is not drawn where it was spawned");
+
+    // シェーダーでも何周しても画面内に折り返す
+    ctx.draw_at(20.0);
+    let pixels = ctx.read_pixels().unwrap();
+    let lit = pixels.chunks_exact(4).filter(|pixel| pixel[3] > 0).count();
+    assert!(lit > 0, "burst left the screen");
+}
+
+#[test]
+fn touch_gestures() {
+    assert_matches_golden_images(vec![Scene {
+        action: Some(pinch_and_drag),
+        ..Scene::new("field_seed_42_pinch_and_drag", StarInstance::new_vec_seeded(200, 42))
+    }]);
+}
+
+#[test]
+fn collisions() {
+    assert_matches_golden_images(vec![Scene {
+        simulation: SimulationMode::Cpu,
+        action: Some(bounce_and_collide),
+        time: 0.6,
+        previous_times: (0..30).map(|frame| frame as f32 * 0.02).collect(),
+        ..Scene::new("bouncing_stars", bouncing_stars())
+    }]);
+}
+
+#[test]
+fn force_fields() {
+    assert_matches_golden_images(vec![Scene {
+        simulation: SimulationMode::Cpu,
+        action: Some(gravity_wind_and_vortex),
+        time: 1.0,
+        previous_times: (0..20).map(|frame| frame as f32 * 0.05).collect(),
+        ..Scene::new("field_seed_42_forces", StarInstance::new_vec_seeded(200, 42))
+    }]);
+}
+
+#[test]
+fn boids() {
+    assert_matches_golden_images(vec![Scene {
+        simulation: SimulationMode::Cpu,
+        action: Some(|ctx| ctx.boids = Some(BoidsSettings::default())),
+        time: 3.0,
+        previous_times: (0..60).map(|frame| frame as f32 * 0.05).collect(),
+        ..Scene::new("field_seed_42_boids", StarInstance::new_vec_seeded(200, 42))
+    }]);
+}
+
+#[test]
+fn boids_point_along_their_velocity() {
+    for degrees in [90.0f32, 30.0, 200.0, 315.0] {
+        let heading = [degrees.to_radians().cos(), degrees.to_radians().sin()];
+        let pixels = render(Scene {
+            simulation: SimulationMode::Cpu,
+            action: Some(|ctx| ctx.boids = Some(BoidsSettings::default())),
+            ..Scene::new("boid_heading", vec![StarInstance { speed: heading.map(|h| h * 0.1), ..single_star(0.0)[0] }])
+        });
+        let background = pixel_at(&pixels, [-0.95, -0.95]);
+        let covered = |at: [f32; 2]| {
+            pixel_at(&pixels, at).iter().zip(background).any(|(pixel, background)| pixel.abs_diff(background) > 64)
+        };
+        // 先端は進む向きにあり、反対側は2つの頂点の間のくぼみになる
+        let tip = heading.map(|h| h * 0.8 * 0.9);
+        assert!(covered(tip), "{} degrees: no tip at {:?}", degrees, tip);
+        assert!(!covered(tip.map(|t| -t)), "{} degrees: a tip points backwards", degrees);
+    }
+}
+
+#[test]
+fn emitters() {
+    assert_matches_golden_images(vec![Scene {
+        simulation: SimulationMode::Cpu,
+        action: Some(four_emitters),
+        time: 1.5,
+        previous_times: (0..30).map(|frame| frame as f32 * 0.05).collect(),
+        ..Scene::new("emitters", Vec::new())
+    }]);
+}
+
+#[test]
+fn lifetime_curves() {
+    assert_matches_golden_images(vec![
+        Scene {
+            simulation: SimulationMode::Cpu,
+            action: Some(emitter_with_curves),
+            time: 1.5,
+            previous_times: (0..30).map(|frame| frame as f32 * 0.05).collect(),
+            ..Scene::new("emitter_lifetime_curves", Vec::new())
+        },
+        // SDFでも色の曲線で塗る
+        Scene {
+            simulation: SimulationMode::Cpu,
+            render_mode: StarRenderMode::Sdf,
+            action: Some(emitter_with_curves),
+            time: 1.5,
+            previous_times: (0..30).map(|frame| frame as f32 * 0.05).collect(),
+            ..Scene::new("emitter_lifetime_curves_sdf", Vec::new())
+        },
+    ]);
+}
+
+#[test]
+fn fireworks() {
+    assert_matches_golden_images(vec![Scene {
+        simulation: SimulationMode::Cpu,
+        action: Some(launch_fireworks),
+        time: 2.5,
+        previous_times: (0..50).map(|frame| frame as f32 * 0.05).collect(),
+        background: background(Background::Solid([0.0, 0.0, 0.02, 1.0]), 0),
+        ..Scene::new("fireworks", Vec::new())
+    }]);
+}
+
+#[test]
+fn screenshot_saves_the_drawn_frame() {
+    let mut ctx = WgpuStarCtx::new_software(SIZE, SIZE);
+    ctx.set_instances(single_star(0.3));
+    let path = diff_dir().join("screenshot.png");
+    std::fs::create_dir_all(diff_dir()).unwrap();
+    ctx.request_screenshot(path.clone());
+    ctx.draw_at(0.0);
+    assert_eq!(read_png(&path).unwrap(), ctx.read_pixels().unwrap());
+}
+
+#[test]
+fn interaction_reuses_emitter_slots() {
+    use rand::SeedableRng;
+    let mut ctx = WgpuStarCtx::new_software(SIZE, SIZE);
+    ctx.simulation = SimulationMode::Cpu;
+    ctx.set_emitters(EmitterSystem::seeded(vec![Emitter::new(EmitterShape::Point([0.5, 0.5]))], 256, 1));
+    for frame in 0..10 {
+        ctx.draw_at(frame as f32 * 0.05);
+    }
+    let alive = |ctx: &WgpuStarCtx| ctx.emitters.as_ref().unwrap().lives().iter().filter(|life| life.is_alive()).count();
+    let (before, buffer_size) = (alive(&ctx), ctx.instance_buffer.size());
+
+    ctx.spawn_burst([-0.5, -0.5]);
+    assert_eq!(alive(&ctx), before + ctx.interaction.burst_count as usize);
+    assert_eq!(ctx.remove_stars_near([-0.5, -0.5]), ctx.interaction.burst_count as usize);
+    assert_eq!(alive(&ctx), before);
+    ctx.add_instances(interaction::spawn_burst([0.0, 0.0], 12, &mut rand::rngs::StdRng::seed_from_u64(7)));
+    assert_eq!(alive(&ctx), before + 12);
+    ctx.draw_at(0.5);
+
+    // 枠の数もインスタンスバッファも変わらない
+    assert_eq!((ctx.instances.len(), ctx.instance_count), (256, 256));
+    assert_eq!(ctx.instance_buffer.size(), buffer_size);
+}